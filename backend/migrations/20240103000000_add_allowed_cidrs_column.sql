@@ -0,0 +1,2 @@
+-- Restrict downloads to client networks (comma-separated CIDRs, NULL = unrestricted)
+ALTER TABLE download_links ADD COLUMN allowed_cidrs TEXT;
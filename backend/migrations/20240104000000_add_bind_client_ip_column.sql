@@ -0,0 +1,2 @@
+-- Per-link override for binding signed OSS URLs to the downloader's IP (NULL = global default)
+ALTER TABLE download_links ADD COLUMN bind_client_ip INTEGER;
@@ -0,0 +1,3 @@
+-- Optional human-readable slug used in place of the ID in download URLs
+ALTER TABLE download_links ADD COLUMN slug TEXT;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_download_links_slug ON download_links(slug);
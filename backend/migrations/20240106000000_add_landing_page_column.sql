@@ -0,0 +1,2 @@
+-- Show an interstitial page with file details before redirecting to the download
+ALTER TABLE download_links ADD COLUMN landing_page INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Requests from link-preview bots and HEAD probes, which do not count as downloads
+ALTER TABLE download_links ADD COLUMN preview_hits INTEGER NOT NULL DEFAULT 0;
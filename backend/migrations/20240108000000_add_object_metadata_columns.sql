@@ -0,0 +1,3 @@
+-- Size and ETag of the shared object, captured with a HEAD request at creation
+ALTER TABLE download_links ADD COLUMN object_size INTEGER;
+ALTER TABLE download_links ADD COLUMN object_etag TEXT;
@@ -0,0 +1,2 @@
+-- Estimated completion of a pending restore for links to archived objects
+ALTER TABLE download_links ADD COLUMN restore_ready_at TEXT;
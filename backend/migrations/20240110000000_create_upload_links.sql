@@ -0,0 +1,13 @@
+-- Links that let an anonymous holder upload files into a bucket prefix
+CREATE TABLE IF NOT EXISTS upload_links (
+    id TEXT PRIMARY KEY NOT NULL,
+    bucket TEXT,
+    endpoint TEXT,
+    prefix TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    max_uploads INTEGER,
+    uploads_served INTEGER NOT NULL DEFAULT 0,
+    max_file_size INTEGER,
+    allowed_content_types TEXT,
+    created_at TEXT NOT NULL
+);
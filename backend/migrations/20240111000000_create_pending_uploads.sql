@@ -0,0 +1,17 @@
+-- Multipart uploads streamed through POST /objects/upload, kept until completed or aborted
+CREATE TABLE IF NOT EXISTS pending_uploads (
+    upload_id TEXT PRIMARY KEY NOT NULL,
+    bucket TEXT NOT NULL,
+    endpoint TEXT,
+    object_key TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS pending_upload_parts (
+    upload_id TEXT NOT NULL,
+    part_number INTEGER NOT NULL,
+    etag TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    PRIMARY KEY (upload_id, part_number)
+);
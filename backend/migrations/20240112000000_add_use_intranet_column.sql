@@ -0,0 +1,2 @@
+-- Per-link choice of the bucket's intranet host (NULL = decide by the downloader's network)
+ALTER TABLE download_links ADD COLUMN use_intranet INTEGER;
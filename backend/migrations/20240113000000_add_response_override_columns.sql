@@ -0,0 +1,5 @@
+-- x-oss-process and response header overrides signed into each download URL
+ALTER TABLE download_links ADD COLUMN process TEXT;
+ALTER TABLE download_links ADD COLUMN response_content_type TEXT;
+ALTER TABLE download_links ADD COLUMN response_cache_control TEXT;
+ALTER TABLE download_links ADD COLUMN response_content_language TEXT;
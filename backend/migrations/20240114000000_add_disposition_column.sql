@@ -0,0 +1,2 @@
+-- Per-link Content-Disposition type, inline or attachment (NULL = attachment when a filename is set)
+ALTER TABLE download_links ADD COLUMN disposition TEXT;
@@ -0,0 +1,3 @@
+-- Recipient of the link and how downloads are watermarked for them (NULL = plain redirect)
+ALTER TABLE download_links ADD COLUMN recipient TEXT;
+ALTER TABLE download_links ADD COLUMN watermark TEXT;
@@ -0,0 +1,11 @@
+-- Uploads started through an upload link; completing one requires the key and upload ID issued here
+CREATE TABLE IF NOT EXISTS upload_sessions (
+    link_id TEXT NOT NULL,
+    object_key TEXT NOT NULL,
+    upload_id TEXT,
+    bucket TEXT NOT NULL,
+    endpoint TEXT,
+    expires_at TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (link_id, object_key)
+);
@@ -0,0 +1,2 @@
+-- Whether completing a pending upload may replace an existing object (1 = refuse)
+ALTER TABLE pending_uploads ADD COLUMN forbid_overwrite INTEGER NOT NULL DEFAULT 1;
@@ -6,10 +6,11 @@ use axum::{
     http::{StatusCode, header, request::Parts},
     response::{IntoResponse, Response},
 };
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use jsonwebtoken::encode;
 use serde::{Deserialize, Serialize};
 
 use crate::config::AppConfig;
+use crate::jwt_keys::JwtKeys;
 use crate::state::AppState;
 
 #[derive(Debug, Clone)]
@@ -76,15 +77,13 @@ where
             .strip_prefix("Bearer ")
             .ok_or(AuthError::InvalidFormat)?;
 
-        let decoded = decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(app_state.config.jwt_secret.as_bytes()),
-            &Validation::default(),
-        )
-        .map_err(|_| AuthError::InvalidToken)?;
+        let claims = app_state
+            .jwt_keys
+            .verify::<Claims>(token)
+            .ok_or(AuthError::InvalidToken)?;
 
         Ok(Self {
-            username: decoded.claims.sub,
+            username: claims.sub,
         })
     }
 }
@@ -92,6 +91,7 @@ where
 pub fn generate_token(
     username: &str,
     config: &AppConfig,
+    keys: &JwtKeys,
 ) -> Result<String, jsonwebtoken::errors::Error> {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -105,9 +105,5 @@ pub fn generate_token(
         iat: now,
     };
 
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(config.jwt_secret.as_bytes()),
-    )
+    encode(keys.header(), &claims, keys.encoding_key())
 }
@@ -0,0 +1,119 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRef, FromRequestParts},
+    http::{HeaderMap, StatusCode, request::Parts},
+};
+use ipnet::IpNet;
+
+use crate::state::AppState;
+
+/// Address of the downloader, resolved through the configured trusted proxies
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub IpAddr);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientIp
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let app_state = AppState::from_ref(state);
+
+        let ConnectInfo(peer) = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .copied()
+            .ok_or_else(|| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Client address is unavailable".to_string(),
+                )
+            })?;
+
+        Ok(Self(resolve_client_ip(
+            peer.ip(),
+            &parts.headers,
+            &app_state.config.trusted_proxies,
+        )))
+    }
+}
+
+/// Walk `X-Forwarded-For` from the nearest hop outwards, stopping at the first
+/// address that is not one of our own proxies. Anything further left could have
+/// been written by the client and is ignored.
+///
+/// IPv4-mapped IPv6 addresses (`::ffff:10.0.0.1`, as seen on dual-stack listeners)
+/// are returned as plain IPv4 so they match IPv4 CIDRs downstream.
+pub fn resolve_client_ip(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpNet]) -> IpAddr {
+    let peer = peer.to_canonical();
+    let is_trusted = |ip: &IpAddr| trusted_proxies.iter().any(|net| net.contains(ip));
+
+    if !is_trusted(&peer) {
+        return peer;
+    }
+
+    let forwarded: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect();
+
+    let mut client = peer;
+    for hop in forwarded.into_iter().rev() {
+        // A malformed entry means we can no longer trust anything to its left
+        let Ok(hop) = hop.trim().parse::<IpAddr>() else {
+            break;
+        };
+        let hop = hop.to_canonical();
+        client = hop;
+        if !is_trusted(&hop) {
+            break;
+        }
+    }
+    client
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn forwarded_for(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn canonicalizes_ipv4_mapped_addresses() {
+        let proxies: Vec<IpNet> = vec!["10.0.0.0/8".parse().unwrap()];
+        let internal: IpNet = "192.168.0.0/16".parse().unwrap();
+
+        // Direct connection on a dual-stack socket
+        let peer: IpAddr = "::ffff:192.168.1.5".parse().unwrap();
+        let client = resolve_client_ip(peer, &HeaderMap::new(), &proxies);
+        assert_eq!(client, "192.168.1.5".parse::<IpAddr>().unwrap());
+        assert!(internal.contains(&client));
+
+        // A mapped proxy address is still trusted, and a mapped hop is unwrapped
+        let peer: IpAddr = "::ffff:10.0.0.2".parse().unwrap();
+        let headers = forwarded_for("::ffff:192.168.1.5, ::ffff:10.0.0.3");
+        let client = resolve_client_ip(peer, &headers, &proxies);
+        assert_eq!(client, "192.168.1.5".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn ignores_forwarded_for_from_untrusted_peers() {
+        let proxies: Vec<IpNet> = vec!["10.0.0.0/8".parse().unwrap()];
+        let peer: IpAddr = "203.0.113.7".parse().unwrap();
+        let headers = forwarded_for("192.168.1.5");
+        assert_eq!(resolve_client_ip(peer, &headers, &proxies), peer);
+    }
+}
@@ -1,30 +1,69 @@
+use std::collections::HashMap;
 use std::env;
 use std::str::FromStr;
 
+use chrono::{DateTime, Utc};
+use ipnet::IpNet;
+use jsonwebtoken::Algorithm;
 use thiserror::Error;
 
+use crate::cdn::{CdnDomain, parse_cdn_domains};
+use crate::link_id::LinkIdFormat;
+use crate::link_preview::{DEFAULT_PREVIEW_USER_AGENTS, PreviewAction};
+use crate::oss_client::RestoreTier;
+use crate::rate_limit::{RateLimit, RateLimitSetting};
+
 #[derive(Debug, Clone)]
 pub struct AppConfig {
     pub api_host: String,
     pub api_port: u16,
     pub public_base_url: String,
     pub download_prefix: String,
+    pub upload_prefix: String,
     pub aliyun_access_key_id: String,
     pub aliyun_access_key_secret: String,
     pub aliyun_default_endpoint: Option<String>,
     pub aliyun_default_bucket: Option<String>,
+    pub aliyun_region: Option<String>,
+    pub oss_bind_source_ip: bool,
+    pub oss_source_ip_prefix_len: u8,
+    pub oss_internal_networks: Vec<IpNet>,
+    pub cdn_domains: HashMap<String, CdnDomain>,
+    pub oss_strict_object_check: bool,
+    pub oss_auto_restore: bool,
+    pub oss_restore_days: u32,
+    pub oss_restore_tier: RestoreTier,
+    pub oss_bucket_cache_ttl_secs: u64,
+    pub search_max_scanned_keys: u64,
+    pub watermark_max_bytes: u64,
+    pub watermark_max_concurrent: usize,
+    pub pending_upload_expiry_hours: i64,
     pub default_expiry_secs: i64,
-    pub jwt_secret: String,
+    pub link_id_format: LinkIdFormat,
+    pub preview_user_agents: Vec<String>,
+    pub preview_action: PreviewAction,
+    pub jwt_secret: Option<String>,
     pub jwt_exp_minutes: i64,
+    pub jwt_algorithm: Algorithm,
+    pub jwt_signing_key_path: Option<String>,
+    pub jwt_verification_key_paths: Vec<String>,
+    pub jwt_legacy_accept_until: Option<DateTime<Utc>>,
     pub oauth_client_id: String,
     pub oauth_client_secret: String,
-    #[allow(dead_code)]
     pub oauth_authorize_url: String,
     pub oauth_token_url: String,
     pub oauth_userinfo_url: String,
-    #[allow(dead_code)]
     pub oauth_redirect_uri: String,
+    pub oauth_issuer_url: Option<String>,
+    pub oauth_admin_role: Option<String>,
+    pub oauth_scope: String,
     pub cors_allowed_origins: Vec<String>,
+    pub trusted_proxies: Vec<IpNet>,
+    pub rate_limit_download_per_ip: Option<RateLimit>,
+    pub rate_limit_download_per_link: Option<RateLimit>,
+    pub rate_limit_auth_per_ip: Option<RateLimit>,
+    pub rate_limit_redis_url: Option<String>,
+    pub metrics_token: Option<String>,
 }
 
 #[derive(Debug, Error)]
@@ -45,6 +84,8 @@ impl AppConfig {
         let download_prefix =
             env::var("DOWNLOAD_PATH_PREFIX").unwrap_or_else(|_| "download".to_string());
         let download_prefix = trim_slashes(&download_prefix).to_string();
+        let upload_prefix = env::var("UPLOAD_PATH_PREFIX").unwrap_or_else(|_| "upload".to_string());
+        let upload_prefix = trim_slashes(&upload_prefix).to_string();
 
         let aliyun_access_key_id = require_env("ALIYUN_ACCESS_KEY_ID")?;
         let aliyun_access_key_secret = require_env("ALIYUN_ACCESS_KEY_SECRET")?;
@@ -55,9 +96,122 @@ impl AppConfig {
             .ok()
             .filter(|s| !s.is_empty());
 
+        // Region for V4 signing; derived from the endpoint when unset
+        let aliyun_region = env::var("ALIYUN_REGION").ok().filter(|s| !s.is_empty());
+        let oss_bind_source_ip = parse_with_default("OSS_BIND_SOURCE_IP", false)?;
+        // Refuse to create links for keys that do not exist
+        let oss_strict_object_check = parse_with_default("OSS_STRICT_OBJECT_CHECK", false)?;
+        // Restoring archived objects when a link is created for them
+        let oss_auto_restore = parse_with_default("OSS_AUTO_RESTORE", false)?;
+        let oss_restore_days = parse_with_default("OSS_RESTORE_DAYS", 1u32)?;
+        if !(1..=365).contains(&oss_restore_days) {
+            return Err(ConfigError::ParseError(
+                "OSS_RESTORE_DAYS",
+                "must be between 1 and 365".to_string(),
+            ));
+        }
+        let oss_restore_tier = parse_with_default("OSS_RESTORE_TIER", RestoreTier::Standard)?;
+        // How long a bucket's region is remembered before GetBucketLocation is asked again
+        let oss_bucket_cache_ttl_secs = parse_with_default("OSS_BUCKET_CACHE_TTL_SECS", 3600u64)?;
+        // Upper bound on keys one object search may list, whatever the request asks for
+        let search_max_scanned_keys = parse_with_default("SEARCH_MAX_SCANNED_KEYS", 100_000u64)?;
+        if search_max_scanned_keys == 0 {
+            return Err(ConfigError::ParseError(
+                "SEARCH_MAX_SCANNED_KEYS",
+                "must be at least 1".to_string(),
+            ));
+        }
+        // Watermarked downloads are held in memory while they are rewritten
+        let watermark_max_bytes = parse_with_default("WATERMARK_MAX_BYTES", 100 * 1024 * 1024u64)?;
+        // ...so only this many are fetched and rewritten at once
+        let watermark_max_concurrent = parse_with_default("WATERMARK_MAX_CONCURRENT", 4usize)?;
+        if watermark_max_concurrent == 0 {
+            return Err(ConfigError::ParseError(
+                "WATERMARK_MAX_CONCURRENT",
+                "must be at least 1".to_string(),
+            ));
+        }
+        // Unfinished multipart uploads are aborted this long after they were started
+        let pending_upload_expiry_hours = parse_with_default("PENDING_UPLOAD_EXPIRY_HOURS", 24i64)?;
+        if pending_upload_expiry_hours < 1 {
+            return Err(ConfigError::ParseError(
+                "PENDING_UPLOAD_EXPIRY_HOURS",
+                "must be at least 1".to_string(),
+            ));
+        }
+        let oss_source_ip_prefix_len = parse_with_default("OSS_SOURCE_IP_PREFIX_LEN", 32u8)?;
+        if oss_source_ip_prefix_len > 32 {
+            return Err(ConfigError::ParseError(
+                "OSS_SOURCE_IP_PREFIX_LEN",
+                "must be between 0 and 32".to_string(),
+            ));
+        }
+
+        // Downloaders in these networks (e.g. our ECS VPCs) get the bucket's intranet host
+        let oss_internal_networks = env::var("OSS_INTERNAL_NETWORKS")
+            .map(|value| parse_list(&value))
+            .unwrap_or_default()
+            .iter()
+            .map(|cidr| {
+                cidr.parse::<IpNet>().map_err(|err| {
+                    ConfigError::ParseError("OSS_INTERNAL_NETWORKS", err.to_string())
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Buckets fronted by a CDN domain redirect downloads there instead of to OSS
+        let cdn_domains = match env::var("CDN_DOMAINS") {
+            Ok(value) if !value.trim().is_empty() => parse_cdn_domains(&value)
+                .map_err(|err| ConfigError::ParseError("CDN_DOMAINS", err))?,
+            _ => HashMap::new(),
+        };
+
         let default_expiry_secs = parse_with_default("DEFAULT_EXPIRY_SECS", 3600i64)?;
-        let jwt_secret = require_env("JWT_SECRET")?;
+        let link_id_format = parse_with_default("LINK_ID_FORMAT", LinkIdFormat::Uuid)?;
+        // Unfurlers must not use up downloads; an empty list turns detection off
+        let preview_user_agents = env::var("PREVIEW_USER_AGENTS")
+            .map(|value| parse_list(&value))
+            .unwrap_or_else(|_| {
+                DEFAULT_PREVIEW_USER_AGENTS
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect()
+            })
+            .iter()
+            .map(|s| s.to_ascii_lowercase())
+            .collect();
+        let preview_action =
+            parse_with_default("PREVIEW_USER_AGENT_ACTION", PreviewAction::Ignore)?;
+        let jwt_secret = env::var("JWT_SECRET").ok().filter(|s| !s.is_empty());
         let jwt_exp_minutes = parse_with_default("JWT_EXP_MINUTES", 60i64)?;
+        let jwt_algorithm = parse_with_default("JWT_ALGORITHM", Algorithm::HS256)?;
+        let jwt_signing_key_path = env::var("JWT_SIGNING_KEY_PATH")
+            .ok()
+            .filter(|s| !s.is_empty());
+        let jwt_verification_key_paths = env::var("JWT_VERIFICATION_KEY_PATHS")
+            .map(|value| parse_list(&value))
+            .unwrap_or_default();
+
+        // After moving to a key pair, HS256 tokens signed with JWT_SECRET are accepted
+        // until this instant (set it to when the last of them expires)
+        let jwt_legacy_accept_until = env::var("JWT_LEGACY_ACCEPT_UNTIL")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .map(|value| {
+                value.parse::<DateTime<Utc>>().map_err(|err| {
+                    ConfigError::ParseError("JWT_LEGACY_ACCEPT_UNTIL", err.to_string())
+                })
+            })
+            .transpose()?;
+
+        if (jwt_algorithm == Algorithm::HS256 || jwt_legacy_accept_until.is_some())
+            && jwt_secret.is_none()
+        {
+            return Err(ConfigError::MissingVar("JWT_SECRET"));
+        }
+        if jwt_algorithm != Algorithm::HS256 && jwt_signing_key_path.is_none() {
+            return Err(ConfigError::MissingVar("JWT_SIGNING_KEY_PATH"));
+        }
 
         let oauth_client_id = require_env("OAUTH_CLIENT_ID")?;
         let oauth_client_secret = require_env("OAUTH_CLIENT_SECRET")?;
@@ -68,37 +222,122 @@ impl AppConfig {
         let oauth_userinfo_url = env::var("OAUTH_USERINFO_URL")
             .unwrap_or_else(|_| "https://sso.honahec.cc/oauth/userinfo/".to_string());
         let oauth_redirect_uri = require_env("OAUTH_REDIRECT_URI")?;
+        // When an issuer is set, endpoints come from OIDC discovery instead of the URLs above
+        let oauth_issuer_url = env::var("OAUTH_ISSUER_URL").ok().filter(|s| !s.is_empty());
+        let oauth_admin_role = env::var("OAUTH_ADMIN_ROLE").ok().filter(|s| !s.is_empty());
+        let default_scope = if oauth_issuer_url.is_some() {
+            "openid profile email"
+        } else {
+            "username permissions"
+        };
+        let oauth_scope = env::var("OAUTH_SCOPE").unwrap_or_else(|_| default_scope.to_string());
 
         let cors_allowed_origins = env::var("CORS_ALLOWED_ORIGINS")
             .map(|value| parse_origins(&value))
             .unwrap_or_else(|_| vec!["*".to_string()]);
 
+        // Reverse proxies whose X-Forwarded-For we believe when resolving client IPs
+        let trusted_proxies = env::var("TRUSTED_PROXIES")
+            .map(|value| parse_list(&value))
+            .unwrap_or_default()
+            .iter()
+            .map(|cidr| {
+                cidr.parse::<IpNet>()
+                    .map_err(|err| ConfigError::ParseError("TRUSTED_PROXIES", err.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Limits look like "60/m"; "off" disables one
+        let RateLimitSetting(rate_limit_download_per_ip) = parse_with_default(
+            "RATE_LIMIT_DOWNLOAD_PER_IP",
+            RateLimitSetting(Some(RateLimit::per_minute(60))),
+        )?;
+        let RateLimitSetting(rate_limit_download_per_link) = parse_with_default(
+            "RATE_LIMIT_DOWNLOAD_PER_LINK",
+            RateLimitSetting(Some(RateLimit::per_minute(120))),
+        )?;
+        let RateLimitSetting(rate_limit_auth_per_ip) = parse_with_default(
+            "RATE_LIMIT_AUTH_PER_IP",
+            RateLimitSetting(Some(RateLimit::per_minute(10))),
+        )?;
+        // Share buckets between instances (requires the `redis` feature)
+        let rate_limit_redis_url = env::var("RATE_LIMIT_REDIS_URL")
+            .ok()
+            .filter(|s| !s.is_empty());
+
+        // Scrapers must send it as a bearer token; without it /metrics is public
+        let metrics_token = env::var("METRICS_TOKEN").ok().filter(|s| !s.is_empty());
+
         Ok(Self {
             api_host,
             api_port,
             public_base_url,
             download_prefix,
+            upload_prefix,
             aliyun_access_key_id,
             aliyun_access_key_secret,
             aliyun_default_endpoint,
             aliyun_default_bucket,
+            aliyun_region,
+            oss_bind_source_ip,
+            oss_source_ip_prefix_len,
+            oss_internal_networks,
+            cdn_domains,
+            oss_strict_object_check,
+            oss_auto_restore,
+            oss_restore_days,
+            oss_restore_tier,
+            oss_bucket_cache_ttl_secs,
+            search_max_scanned_keys,
+            watermark_max_bytes,
+            watermark_max_concurrent,
+            pending_upload_expiry_hours,
             default_expiry_secs,
+            link_id_format,
+            preview_user_agents,
+            preview_action,
             jwt_secret,
             jwt_exp_minutes,
+            jwt_algorithm,
+            jwt_signing_key_path,
+            jwt_verification_key_paths,
+            jwt_legacy_accept_until,
             oauth_client_id,
             oauth_client_secret,
             oauth_authorize_url,
             oauth_token_url,
             oauth_userinfo_url,
             oauth_redirect_uri,
+            oauth_issuer_url,
+            oauth_admin_role,
+            oauth_scope,
             cors_allowed_origins,
+            trusted_proxies,
+            rate_limit_download_per_ip,
+            rate_limit_download_per_link,
+            rate_limit_auth_per_ip,
+            rate_limit_redis_url,
+            metrics_token,
         })
     }
 
-    #[allow(dead_code)]
+    /// `CORS_ALLOWED_ORIGINS=*`: any site may call the API, but never with credentials
+    pub fn allows_any_origin(&self) -> bool {
+        self.cors_allowed_origins.iter().any(|origin| origin == "*")
+    }
+
+    /// Cookies get the `Secure` attribute when the backend is served over HTTPS
+    pub fn secure_cookies(&self) -> bool {
+        self.public_base_url.starts_with("https://")
+    }
+
     pub fn download_base_url(&self) -> String {
         format!("{}/{}/", self.public_base_url, self.download_prefix)
     }
+
+    pub fn upload_base_url(&self) -> String {
+        format!("{}/{}/", self.public_base_url, self.upload_prefix)
+    }
 }
 
 fn require_env(key: &'static str) -> Result<String, ConfigError> {
@@ -135,10 +374,14 @@ fn parse_origins(value: &str) -> Vec<String> {
     if value.trim() == "*" {
         vec!["*".to_string()]
     } else {
-        value
-            .split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect()
+        parse_list(value)
     }
 }
+
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
@@ -0,0 +1,175 @@
+use std::str::FromStr;
+
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::{Deserialize, Serialize};
+
+// RFC 5987 attr-char: everything else in an ext-value is percent-encoded
+const ATTR_CHAR: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
+/// Whether browsers should display the file or save it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DispositionType {
+    Inline,
+    Attachment,
+}
+
+impl DispositionType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DispositionType::Inline => "inline",
+            DispositionType::Attachment => "attachment",
+        }
+    }
+}
+
+impl FromStr for DispositionType {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "inline" => Ok(DispositionType::Inline),
+            "attachment" => Ok(DispositionType::Attachment),
+            other => Err(format!("expected inline or attachment, got {:?}", other)),
+        }
+    }
+}
+
+/// RFC 6266 header value. Non-ASCII names get an RFC 5987 `filename*` next to an
+/// ASCII `filename` for clients that ignore it, e.g.
+/// `attachment; filename="_.pdf"; filename*=UTF-8''%E6%8A%A5%E5%91%8A.pdf`
+pub fn content_disposition(kind: DispositionType, filename: Option<&str>) -> String {
+    let Some(filename) = filename
+        .map(sanitize_filename)
+        .filter(|name| !name.is_empty())
+    else {
+        return kind.as_str().to_string();
+    };
+
+    let fallback = ascii_fallback(&filename);
+    if fallback == filename {
+        return format!("{}; filename=\"{}\"", kind.as_str(), fallback);
+    }
+
+    format!(
+        "{}; filename=\"{}\"; filename*=UTF-8''{}",
+        kind.as_str(),
+        fallback,
+        utf8_percent_encode(&filename, ATTR_CHAR)
+    )
+}
+
+/// Keep only the last path component and drop control and bidi-override characters,
+/// which could split the header or disguise the extension
+pub fn sanitize_filename(filename: &str) -> String {
+    let name = filename.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name
+        .chars()
+        .filter(|c| !c.is_control() && !is_bidi_control(*c))
+        .collect();
+    let name = name.trim();
+    match name {
+        "." | ".." => String::new(),
+        name => name.to_string(),
+    }
+}
+
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+// Each run of characters that cannot appear in a quoted-string becomes one '_'
+fn ascii_fallback(filename: &str) -> String {
+    let mut fallback = String::with_capacity(filename.len());
+    for c in filename.chars() {
+        let safe = c.is_ascii_graphic() && !matches!(c, '"' | '\\' | ';' | '%') || c == ' ';
+        if safe {
+            fallback.push(c);
+        } else if !fallback.ends_with('_') {
+            fallback.push('_');
+        }
+    }
+    fallback
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attachment(filename: &str) -> String {
+        content_disposition(DispositionType::Attachment, Some(filename))
+    }
+
+    #[test]
+    fn encodes_non_ascii_names() {
+        assert_eq!(
+            attachment("报告.pdf"),
+            "attachment; filename=\"_.pdf\"; filename*=UTF-8''%E6%8A%A5%E5%91%8A.pdf"
+        );
+        assert_eq!(
+            attachment("📄 notes.txt"),
+            "attachment; filename=\"_ notes.txt\"; filename*=UTF-8''%F0%9F%93%84%20notes.txt"
+        );
+    }
+
+    #[test]
+    fn header_cannot_be_split_or_unquoted() {
+        assert_eq!(
+            attachment("a\r\nb;c\"d.pdf"),
+            "attachment; filename=\"ab_c_d.pdf\"; filename*=UTF-8''ab%3Bc%22d.pdf"
+        );
+    }
+
+    #[test]
+    fn keeps_only_the_last_path_component() {
+        assert_eq!(sanitize_filename("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_filename("C:\\x\\y.txt"), "y.txt");
+    }
+
+    #[test]
+    fn drops_bidi_overrides_and_dot_names() {
+        assert_eq!(
+            sanitize_filename("invoice\u{202E}fdp.exe"),
+            "invoicefdp.exe"
+        );
+        assert_eq!(
+            sanitize_filename("\u{2067}report.pdf\u{2069}"),
+            "report.pdf"
+        );
+        assert_eq!(sanitize_filename("."), "");
+        assert_eq!(sanitize_filename(".."), "");
+        assert_eq!(attachment(".."), "attachment");
+        assert_eq!(attachment("dir/."), "attachment");
+    }
+
+    #[test]
+    fn names_the_disposition_type() {
+        assert_eq!(
+            content_disposition(DispositionType::Inline, Some("report.pdf")),
+            "inline; filename=\"report.pdf\""
+        );
+        assert_eq!(
+            attachment("report.pdf"),
+            "attachment; filename=\"report.pdf\""
+        );
+        assert_eq!(content_disposition(DispositionType::Inline, None), "inline");
+    }
+
+    #[test]
+    fn fallback_collapses_unsafe_runs() {
+        assert_eq!(ascii_fallback("a;\"%b.txt"), "a_b.txt");
+        assert_eq!(ascii_fallback("日本語 ファイル.txt"), "_ _.txt");
+    }
+}
@@ -1,8 +1,11 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteRow;
 use sqlx::{Pool, Row, Sqlite, SqlitePool};
-use uuid::Uuid;
+
+use crate::content_disposition::DispositionType;
+use crate::watermark::WatermarkMode;
 
 #[derive(Clone)]
 pub struct Database {
@@ -19,10 +22,121 @@ pub struct DownloadLink {
     pub downloads_served: i64,
     pub created_at: DateTime<Utc>,
     pub download_filename: Option<String>,
+    pub disposition: Option<DispositionType>,
+    pub recipient: Option<String>,
+    pub watermark: Option<WatermarkMode>,
     pub endpoint: Option<String>,
+    pub allowed_cidrs: Vec<String>,
+    pub bind_client_ip: Option<bool>,
+    pub use_intranet: Option<bool>,
+    pub process: Option<String>,
+    pub response_content_type: Option<String>,
+    pub response_cache_control: Option<String>,
+    pub response_content_language: Option<String>,
+    pub slug: Option<String>,
+    pub landing_page: bool,
+    pub preview_hits: i64,
+    pub object_size: Option<i64>,
+    pub object_etag: Option<String>,
+    pub restore_ready_at: Option<DateTime<Utc>>,
     pub is_expired: bool,
 }
 
+pub struct NewDownloadLink {
+    pub id: String,
+    pub object_key: String,
+    pub bucket: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub max_downloads: Option<u32>,
+    pub download_filename: Option<String>,
+    pub disposition: Option<DispositionType>,
+    pub recipient: Option<String>,
+    pub watermark: Option<WatermarkMode>,
+    pub endpoint: Option<String>,
+    pub allowed_cidrs: Vec<String>,
+    pub bind_client_ip: Option<bool>,
+    pub use_intranet: Option<bool>,
+    pub process: Option<String>,
+    pub response_content_type: Option<String>,
+    pub response_cache_control: Option<String>,
+    pub response_content_language: Option<String>,
+    pub slug: Option<String>,
+    pub landing_page: bool,
+    pub object_size: Option<u64>,
+    pub object_etag: Option<String>,
+    pub restore_ready_at: Option<DateTime<Utc>>,
+}
+
+/// Lets an anonymous holder upload files under `prefix`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadLink {
+    pub id: String,
+    pub bucket: Option<String>,
+    pub endpoint: Option<String>,
+    pub prefix: String,
+    pub expires_at: DateTime<Utc>,
+    pub max_uploads: Option<i64>,
+    pub uploads_served: i64,
+    pub max_file_size: Option<i64>,
+    pub allowed_content_types: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub is_expired: bool,
+}
+
+pub struct NewUploadLink {
+    pub id: String,
+    pub bucket: Option<String>,
+    pub endpoint: Option<String>,
+    pub prefix: String,
+    pub expires_at: DateTime<Utc>,
+    pub max_uploads: Option<u32>,
+    pub max_file_size: Option<u64>,
+    pub allowed_content_types: Vec<String>,
+}
+
+/// Multipart upload started by `POST /objects/upload` that has not been completed yet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingUpload {
+    pub upload_id: String,
+    pub bucket: String,
+    pub endpoint: Option<String>,
+    pub object_key: String,
+    pub content_type: String,
+    pub forbid_overwrite: bool,
+    pub created_at: DateTime<Utc>,
+    pub parts: Vec<UploadedPart>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadedPart {
+    pub part_number: u32,
+    pub etag: String,
+    pub size: i64,
+}
+
+pub struct NewPendingUpload {
+    pub upload_id: String,
+    pub bucket: String,
+    pub endpoint: Option<String>,
+    pub object_key: String,
+    pub content_type: String,
+    pub forbid_overwrite: bool,
+}
+
+/// Upload handed out by an upload link: the object key and, for multipart, the upload ID
+#[derive(Debug, Clone)]
+pub struct UploadSession {
+    pub link_id: String,
+    pub object_key: String,
+    pub upload_id: Option<String>,
+    pub bucket: String,
+    pub endpoint: Option<String>,
+    /// When the presigned URLs handed out for it stop working
+    pub expires_at: DateTime<Utc>,
+}
+
+const LINK_COLUMNS: &str = "id, object_key, bucket, expires_at, max_downloads, downloads_served, created_at, download_filename, endpoint, allowed_cidrs, bind_client_ip, slug, landing_page, preview_hits, object_size, object_etag, restore_ready_at, use_intranet, process, response_content_type, response_cache_control, response_content_language, disposition, recipient, watermark";
+
 impl Database {
     pub async fn new(database_url: &str) -> Result<Self> {
         use sqlx::sqlite::SqliteConnectOptions;
@@ -72,80 +186,246 @@ impl Database {
             .await
             .ok(); // Ignore errors as the column may already exist
 
+        // Third migration: per-link client network restrictions
+        sqlx::query("ALTER TABLE download_links ADD COLUMN allowed_cidrs TEXT")
+            .execute(&pool)
+            .await
+            .ok();
+
+        // Fourth migration: per-link override for binding signed URLs to the client IP
+        sqlx::query("ALTER TABLE download_links ADD COLUMN bind_client_ip INTEGER")
+            .execute(&pool)
+            .await
+            .ok();
+
+        // Fifth migration: optional human-readable link slugs
+        sqlx::query("ALTER TABLE download_links ADD COLUMN slug TEXT")
+            .execute(&pool)
+            .await
+            .ok();
+
+        sqlx::query(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_download_links_slug ON download_links(slug)",
+        )
+        .execute(&pool)
+        .await?;
+
+        // Sixth migration: optional interstitial page in front of the download
+        sqlx::query(
+            "ALTER TABLE download_links ADD COLUMN landing_page INTEGER NOT NULL DEFAULT 0",
+        )
+        .execute(&pool)
+        .await
+        .ok();
+
+        // Seventh migration: requests from link unfurlers and HEAD probes, kept apart from downloads
+        sqlx::query(
+            "ALTER TABLE download_links ADD COLUMN preview_hits INTEGER NOT NULL DEFAULT 0",
+        )
+        .execute(&pool)
+        .await
+        .ok();
+
+        // Eighth migration: size and ETag of the object when the link was created
+        sqlx::query("ALTER TABLE download_links ADD COLUMN object_size INTEGER")
+            .execute(&pool)
+            .await
+            .ok();
+        sqlx::query("ALTER TABLE download_links ADD COLUMN object_etag TEXT")
+            .execute(&pool)
+            .await
+            .ok();
+
+        // Ninth migration: estimated completion of a pending archive restore
+        sqlx::query("ALTER TABLE download_links ADD COLUMN restore_ready_at TEXT")
+            .execute(&pool)
+            .await
+            .ok();
+
+        // Tenth migration: upload links
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS upload_links (
+                id TEXT PRIMARY KEY NOT NULL,
+                bucket TEXT,
+                endpoint TEXT,
+                prefix TEXT NOT NULL,
+                expires_at TEXT NOT NULL,
+                max_uploads INTEGER,
+                uploads_served INTEGER NOT NULL DEFAULT 0,
+                max_file_size INTEGER,
+                allowed_content_types TEXT,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        // Eleventh migration: server-side multipart uploads and the parts stored so far
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS pending_uploads (
+                upload_id TEXT PRIMARY KEY NOT NULL,
+                bucket TEXT NOT NULL,
+                endpoint TEXT,
+                object_key TEXT NOT NULL,
+                content_type TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS pending_upload_parts (
+                upload_id TEXT NOT NULL,
+                part_number INTEGER NOT NULL,
+                etag TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                PRIMARY KEY (upload_id, part_number)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        // Twelfth migration: per-link choice between the intranet and public OSS host
+        sqlx::query("ALTER TABLE download_links ADD COLUMN use_intranet INTEGER")
+            .execute(&pool)
+            .await
+            .ok();
+
+        // Thirteenth migration: x-oss-process and response header overrides signed into links
+        sqlx::query("ALTER TABLE download_links ADD COLUMN process TEXT")
+            .execute(&pool)
+            .await
+            .ok();
+        sqlx::query("ALTER TABLE download_links ADD COLUMN response_content_type TEXT")
+            .execute(&pool)
+            .await
+            .ok();
+        sqlx::query("ALTER TABLE download_links ADD COLUMN response_cache_control TEXT")
+            .execute(&pool)
+            .await
+            .ok();
+        sqlx::query("ALTER TABLE download_links ADD COLUMN response_content_language TEXT")
+            .execute(&pool)
+            .await
+            .ok();
+
+        // Fourteenth migration: per-link inline or attachment Content-Disposition
+        sqlx::query("ALTER TABLE download_links ADD COLUMN disposition TEXT")
+            .execute(&pool)
+            .await
+            .ok();
+
+        // Fifteenth migration: recipient-bound links served watermarked through the backend
+        sqlx::query("ALTER TABLE download_links ADD COLUMN recipient TEXT")
+            .execute(&pool)
+            .await
+            .ok();
+        sqlx::query("ALTER TABLE download_links ADD COLUMN watermark TEXT")
+            .execute(&pool)
+            .await
+            .ok();
+
+        // Sixteenth migration: uploads started through upload links
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS upload_sessions (
+                link_id TEXT NOT NULL,
+                object_key TEXT NOT NULL,
+                upload_id TEXT,
+                bucket TEXT NOT NULL,
+                endpoint TEXT,
+                expires_at TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (link_id, object_key)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        // Seventeenth migration: whether a pending upload may replace an existing object
+        sqlx::query(
+            "ALTER TABLE pending_uploads ADD COLUMN forbid_overwrite INTEGER NOT NULL DEFAULT 1",
+        )
+        .execute(&pool)
+        .await
+        .ok();
+
         Ok(Self { pool })
     }
 
-    pub async fn create_download_link(
-        &self,
-        id: Uuid,
-        object_key: String,
-        bucket: Option<String>,
-        expires_at: DateTime<Utc>,
-        max_downloads: Option<u32>,
-        download_filename: Option<String>,
-        endpoint: Option<String>,
-    ) -> Result<()> {
-        let expires_at_str = expires_at.to_rfc3339();
+    pub async fn create_download_link(&self, link: NewDownloadLink) -> Result<()> {
+        let expires_at_str = link.expires_at.to_rfc3339();
         let created_at_str = Utc::now().to_rfc3339();
-        let max_downloads_i64 = max_downloads.map(|m| m as i64);
+        let max_downloads_i64 = link.max_downloads.map(|m| m as i64);
+        // Stored comma-separated; NULL means "no restriction"
+        let allowed_cidrs = (!link.allowed_cidrs.is_empty()).then(|| link.allowed_cidrs.join(","));
 
         sqlx::query(
             r#"
-            INSERT INTO download_links (id, object_key, bucket, expires_at, max_downloads, downloads_served, created_at, download_filename, endpoint)
-            VALUES (?, ?, ?, ?, ?, 0, ?, ?, ?)
+            INSERT INTO download_links (id, object_key, bucket, expires_at, max_downloads, downloads_served, created_at, download_filename, endpoint, allowed_cidrs, bind_client_ip, slug, landing_page, object_size, object_etag, restore_ready_at, use_intranet, process, response_content_type, response_cache_control, response_content_language, disposition, recipient, watermark)
+            VALUES (?, ?, ?, ?, ?, 0, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#
         )
-        .bind(id.to_string())
-        .bind(object_key)
-        .bind(bucket)
+        .bind(link.id)
+        .bind(link.object_key)
+        .bind(link.bucket)
         .bind(expires_at_str)
         .bind(max_downloads_i64)
         .bind(created_at_str)
-        .bind(download_filename)
-        .bind(endpoint)
+        .bind(link.download_filename)
+        .bind(link.endpoint)
+        .bind(allowed_cidrs)
+        .bind(link.bind_client_ip)
+        .bind(link.slug)
+        .bind(link.landing_page)
+        .bind(link.object_size.map(|size| size as i64))
+        .bind(link.object_etag)
+        .bind(link.restore_ready_at.map(|at| at.to_rfc3339()))
+        .bind(link.use_intranet)
+        .bind(link.process)
+        .bind(link.response_content_type)
+        .bind(link.response_cache_control)
+        .bind(link.response_content_language)
+        .bind(link.disposition.map(DispositionType::as_str))
+        .bind(link.recipient)
+        .bind(link.watermark.map(WatermarkMode::as_str))
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
-    pub async fn get_download_link(&self, id: &str) -> Result<Option<DownloadLink>> {
-        let row = sqlx::query(
-            "SELECT id, object_key, bucket, expires_at, max_downloads, downloads_served, created_at, download_filename, endpoint FROM download_links WHERE id = ?"
-        )
-        .bind(id)
+    /// Look a link up by its ID or its slug
+    pub async fn get_download_link(&self, id_or_slug: &str) -> Result<Option<DownloadLink>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM download_links WHERE id = ? OR slug = ?",
+            LINK_COLUMNS
+        ))
+        .bind(id_or_slug)
+        .bind(id_or_slug)
         .fetch_optional(&self.pool)
         .await?;
 
-        if let Some(row) = row {
-            let expires_at_str: String = row.get("expires_at");
-            let created_at_str: String = row.get("created_at");
-
-            let expires_at = DateTime::parse_from_rfc3339(&expires_at_str)?.with_timezone(&Utc);
-            let created_at = DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc);
-
-            let max_downloads: Option<i64> = row.get("max_downloads");
-            let downloads_served: i64 = row.get("downloads_served");
+        row.map(|row| link_from_row(&row, Utc::now())).transpose()
+    }
 
-            let now = Utc::now();
-            let is_expired = expires_at < now
-                || (max_downloads.is_some() && downloads_served >= max_downloads.unwrap());
+    /// IDs and slugs share one namespace in download URLs
+    pub async fn identifier_in_use(&self, value: &str) -> Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM download_links WHERE id = ? OR slug = ?")
+            .bind(value)
+            .bind(value)
+            .fetch_optional(&self.pool)
+            .await?;
 
-            Ok(Some(DownloadLink {
-                id: row.get("id"),
-                object_key: row.get("object_key"),
-                bucket: row.get("bucket"),
-                expires_at,
-                max_downloads,
-                downloads_served,
-                created_at,
-                download_filename: row.get("download_filename"),
-                endpoint: row.get("endpoint"),
-                is_expired,
-            }))
-        } else {
-            Ok(None)
-        }
+        Ok(row.is_some())
     }
 
     pub async fn increment_downloads(&self, id: &str) -> Result<()> {
@@ -159,6 +439,30 @@ impl Database {
         Ok(())
     }
 
+    pub async fn record_preview(&self, id: &str) -> Result<()> {
+        sqlx::query("UPDATE download_links SET preview_hits = preview_hits + 1 WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Record a new restore estimate, or clear it once the object is readable
+    pub async fn set_restore_ready_at(
+        &self,
+        id: &str,
+        ready_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        sqlx::query("UPDATE download_links SET restore_ready_at = ? WHERE id = ?")
+            .bind(ready_at.map(|at| at.to_rfc3339()))
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn list_download_links(
         &self,
         limit: Option<i64>,
@@ -167,52 +471,120 @@ impl Database {
         let limit = limit.unwrap_or(50);
         let offset = offset.unwrap_or(0);
 
-        let rows = sqlx::query(
-            "SELECT id, object_key, bucket, expires_at, max_downloads, downloads_served, created_at, download_filename, endpoint 
-             FROM download_links 
-             ORDER BY created_at DESC 
-             LIMIT ? OFFSET ?"
-        )
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM download_links ORDER BY created_at DESC LIMIT ? OFFSET ?",
+            LINK_COLUMNS
+        ))
         .bind(limit)
         .bind(offset)
         .fetch_all(&self.pool)
         .await?;
 
         let now = Utc::now();
-        let mut links = Vec::new();
+        rows.iter().map(|row| link_from_row(row, now)).collect()
+    }
+
+    pub async fn delete_download_link(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM download_links WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete every link to `object_key` in `bucket`, counting links without a bucket
+    /// as pointing at `default_bucket`. Returns the IDs and slugs that were removed.
+    pub async fn delete_links_for_object(
+        &self,
+        bucket: &str,
+        object_key: &str,
+        default_bucket: Option<&str>,
+    ) -> Result<Vec<(String, Option<String>)>> {
+        let rows = sqlx::query(
+            "DELETE FROM download_links WHERE object_key = ? AND (bucket = ? OR (bucket IS NULL AND ? = ?)) RETURNING id, slug",
+        )
+        .bind(object_key)
+        .bind(bucket)
+        .bind(default_bucket)
+        .bind(bucket)
+        .fetch_all(&self.pool)
+        .await?;
 
-        for row in rows {
-            let expires_at_str: String = row.get("expires_at");
-            let created_at_str: String = row.get("created_at");
+        Ok(rows
+            .iter()
+            .map(|row| (row.get("id"), row.get("slug")))
+            .collect())
+    }
 
-            let expires_at = DateTime::parse_from_rfc3339(&expires_at_str)?.with_timezone(&Utc);
-            let created_at = DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc);
+    pub async fn create_upload_link(&self, link: NewUploadLink) -> Result<()> {
+        let allowed_content_types =
+            (!link.allowed_content_types.is_empty()).then(|| link.allowed_content_types.join(","));
 
-            let max_downloads: Option<i64> = row.get("max_downloads");
-            let downloads_served: i64 = row.get("downloads_served");
+        sqlx::query(
+            r#"
+            INSERT INTO upload_links (id, bucket, endpoint, prefix, expires_at, max_uploads, uploads_served, max_file_size, allowed_content_types, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, 0, ?, ?, ?)
+            "#
+        )
+        .bind(link.id)
+        .bind(link.bucket)
+        .bind(link.endpoint)
+        .bind(link.prefix)
+        .bind(link.expires_at.to_rfc3339())
+        .bind(link.max_uploads.map(i64::from))
+        .bind(link.max_file_size.map(|size| size as i64))
+        .bind(allowed_content_types)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
 
-            let is_expired = expires_at < now
-                || (max_downloads.is_some() && downloads_served >= max_downloads.unwrap());
+        Ok(())
+    }
 
-            links.push(DownloadLink {
-                id: row.get("id"),
-                object_key: row.get("object_key"),
-                bucket: row.get("bucket"),
-                expires_at,
-                max_downloads,
-                downloads_served,
-                created_at,
-                download_filename: row.get("download_filename"),
-                endpoint: row.get("endpoint"),
-                is_expired,
-            });
-        }
+    pub async fn get_upload_link(&self, id: &str) -> Result<Option<UploadLink>> {
+        let row = sqlx::query("SELECT * FROM upload_links WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
 
-        Ok(links)
+        row.map(|row| upload_link_from_row(&row, Utc::now()))
+            .transpose()
     }
 
-    pub async fn delete_download_link(&self, id: &str) -> Result<bool> {
-        let result = sqlx::query("DELETE FROM download_links WHERE id = ?")
+    pub async fn list_upload_links(
+        &self,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<Vec<UploadLink>> {
+        let rows =
+            sqlx::query("SELECT * FROM upload_links ORDER BY created_at DESC LIMIT ? OFFSET ?")
+                .bind(limit.unwrap_or(50))
+                .bind(offset.unwrap_or(0))
+                .fetch_all(&self.pool)
+                .await?;
+
+        let now = Utc::now();
+        rows.iter()
+            .map(|row| upload_link_from_row(row, now))
+            .collect()
+    }
+
+    /// Count one upload if the link is still valid; false once expired or used up
+    pub async fn consume_upload(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE upload_links SET uploads_served = uploads_served + 1 WHERE id = ? AND expires_at > ? AND (max_uploads IS NULL OR uploads_served < max_uploads)",
+        )
+        .bind(id)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_upload_link(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM upload_links WHERE id = ?")
             .bind(id)
             .execute(&self.pool)
             .await?;
@@ -220,6 +592,195 @@ impl Database {
         Ok(result.rows_affected() > 0)
     }
 
+    pub async fn create_pending_upload(&self, upload: NewPendingUpload) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO pending_uploads (upload_id, bucket, endpoint, object_key, content_type, forbid_overwrite, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(upload.upload_id)
+        .bind(upload.bucket)
+        .bind(upload.endpoint)
+        .bind(upload.object_key)
+        .bind(upload.content_type)
+        .bind(upload.forbid_overwrite)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The upload with its parts in part-number order
+    pub async fn get_pending_upload(&self, upload_id: &str) -> Result<Option<PendingUpload>> {
+        // Columns are listed so a statement prepared before the forbid_overwrite migration
+        // reached this connection cannot come back one column short
+        let Some(row) = sqlx::query(
+            "SELECT upload_id, bucket, endpoint, object_key, content_type, forbid_overwrite, created_at FROM pending_uploads WHERE upload_id = ?",
+        )
+        .bind(upload_id)
+        .fetch_optional(&self.pool)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let parts = sqlx::query(
+            "SELECT part_number, etag, size FROM pending_upload_parts WHERE upload_id = ? ORDER BY part_number",
+        )
+        .bind(upload_id)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(|row| UploadedPart {
+            part_number: row.get("part_number"),
+            etag: row.get("etag"),
+            size: row.get("size"),
+        })
+        .collect();
+
+        let created_at_str: String = row.get("created_at");
+        let created_at = DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc);
+
+        Ok(Some(PendingUpload {
+            upload_id: row.get("upload_id"),
+            bucket: row.get("bucket"),
+            endpoint: row.get("endpoint"),
+            object_key: row.get("object_key"),
+            content_type: row.get("content_type"),
+            forbid_overwrite: row.get("forbid_overwrite"),
+            created_at,
+            parts,
+        }))
+    }
+
+    pub async fn record_upload_part(&self, upload_id: &str, part: &UploadedPart) -> Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO pending_upload_parts (upload_id, part_number, etag, size) VALUES (?, ?, ?, ?)",
+        )
+        .bind(upload_id)
+        .bind(part.part_number)
+        .bind(&part.etag)
+        .bind(part.size)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Forget the parts numbered `from` and above so they are uploaded again
+    pub async fn delete_upload_parts_from(&self, upload_id: &str, from: u32) -> Result<()> {
+        sqlx::query("DELETE FROM pending_upload_parts WHERE upload_id = ? AND part_number >= ?")
+            .bind(upload_id)
+            .bind(from)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_pending_upload(&self, upload_id: &str) -> Result<bool> {
+        sqlx::query("DELETE FROM pending_upload_parts WHERE upload_id = ?")
+            .bind(upload_id)
+            .execute(&self.pool)
+            .await?;
+        let result = sqlx::query("DELETE FROM pending_uploads WHERE upload_id = ?")
+            .bind(upload_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Uploads started before `before`, without their parts
+    pub async fn list_stale_pending_uploads(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<PendingUpload>> {
+        let rows = sqlx::query(
+            "SELECT upload_id, bucket, endpoint, object_key, content_type, forbid_overwrite, created_at FROM pending_uploads WHERE created_at < ?",
+        )
+        .bind(before.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let created_at_str: String = row.get("created_at");
+                Ok(PendingUpload {
+                    upload_id: row.get("upload_id"),
+                    bucket: row.get("bucket"),
+                    endpoint: row.get("endpoint"),
+                    object_key: row.get("object_key"),
+                    content_type: row.get("content_type"),
+                    forbid_overwrite: row.get("forbid_overwrite"),
+                    created_at: DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc),
+                    parts: Vec::new(),
+                })
+            })
+            .collect()
+    }
+
+    /// Fails with a unique violation if the link already has a session for the key
+    pub async fn create_upload_session(&self, session: &UploadSession) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO upload_sessions (link_id, object_key, upload_id, bucket, endpoint, expires_at, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&session.link_id)
+        .bind(&session.object_key)
+        .bind(&session.upload_id)
+        .bind(&session.bucket)
+        .bind(&session.endpoint)
+        .bind(session.expires_at.to_rfc3339())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_upload_session(
+        &self,
+        link_id: &str,
+        object_key: &str,
+    ) -> Result<Option<UploadSession>> {
+        let row = sqlx::query("SELECT * FROM upload_sessions WHERE link_id = ? AND object_key = ?")
+            .bind(link_id)
+            .bind(object_key)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.map(|row| upload_session_from_row(&row)).transpose()
+    }
+
+    pub async fn delete_upload_session(&self, link_id: &str, object_key: &str) -> Result<bool> {
+        let result =
+            sqlx::query("DELETE FROM upload_sessions WHERE link_id = ? AND object_key = ?")
+                .bind(link_id)
+                .bind(object_key)
+                .execute(&self.pool)
+                .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Sessions whose URLs stopped working before `before`
+    pub async fn list_stale_upload_sessions(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<UploadSession>> {
+        let rows = sqlx::query("SELECT * FROM upload_sessions WHERE expires_at < ?")
+            .bind(before.to_rfc3339())
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter().map(upload_session_from_row).collect()
+    }
+
     pub async fn delete_expired_links(&self) -> Result<u64> {
         let now = Utc::now().to_rfc3339();
         let result = sqlx::query(
@@ -232,3 +793,106 @@ impl Database {
         Ok(result.rows_affected())
     }
 }
+
+fn link_from_row(row: &SqliteRow, now: DateTime<Utc>) -> Result<DownloadLink> {
+    let expires_at_str: String = row.get("expires_at");
+    let created_at_str: String = row.get("created_at");
+
+    let expires_at = DateTime::parse_from_rfc3339(&expires_at_str)?.with_timezone(&Utc);
+    let created_at = DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc);
+
+    let max_downloads: Option<i64> = row.get("max_downloads");
+    let downloads_served: i64 = row.get("downloads_served");
+
+    let is_expired = expires_at < now || max_downloads.is_some_and(|max| downloads_served >= max);
+
+    let restore_ready_at: Option<String> = row.get("restore_ready_at");
+    let restore_ready_at = restore_ready_at
+        .map(|value| DateTime::parse_from_rfc3339(&value).map(|at| at.with_timezone(&Utc)))
+        .transpose()?;
+
+    let disposition: Option<String> = row.get("disposition");
+    let disposition = disposition.and_then(|value| value.parse().ok());
+    let watermark: Option<String> = row.get("watermark");
+    let watermark = watermark.and_then(|value| value.parse().ok());
+
+    let allowed_cidrs: Option<String> = row.get("allowed_cidrs");
+    let allowed_cidrs = allowed_cidrs
+        .map(|value| value.split(',').map(str::to_string).collect())
+        .unwrap_or_default();
+
+    Ok(DownloadLink {
+        id: row.get("id"),
+        object_key: row.get("object_key"),
+        bucket: row.get("bucket"),
+        expires_at,
+        max_downloads,
+        downloads_served,
+        created_at,
+        download_filename: row.get("download_filename"),
+        disposition,
+        recipient: row.get("recipient"),
+        watermark,
+        endpoint: row.get("endpoint"),
+        allowed_cidrs,
+        bind_client_ip: row.get("bind_client_ip"),
+        use_intranet: row.get("use_intranet"),
+        process: row.get("process"),
+        response_content_type: row.get("response_content_type"),
+        response_cache_control: row.get("response_cache_control"),
+        response_content_language: row.get("response_content_language"),
+        slug: row.get("slug"),
+        landing_page: row.get("landing_page"),
+        preview_hits: row.get("preview_hits"),
+        object_size: row.get("object_size"),
+        object_etag: row.get("object_etag"),
+        restore_ready_at,
+        is_expired,
+    })
+}
+
+fn upload_session_from_row(row: &SqliteRow) -> Result<UploadSession> {
+    let expires_at_str: String = row.get("expires_at");
+    let expires_at = DateTime::parse_from_rfc3339(&expires_at_str)?.with_timezone(&Utc);
+
+    Ok(UploadSession {
+        link_id: row.get("link_id"),
+        object_key: row.get("object_key"),
+        upload_id: row.get("upload_id"),
+        bucket: row.get("bucket"),
+        endpoint: row.get("endpoint"),
+        expires_at,
+    })
+}
+
+fn upload_link_from_row(row: &SqliteRow, now: DateTime<Utc>) -> Result<UploadLink> {
+    let expires_at_str: String = row.get("expires_at");
+    let created_at_str: String = row.get("created_at");
+
+    let expires_at = DateTime::parse_from_rfc3339(&expires_at_str)?.with_timezone(&Utc);
+    let created_at = DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&Utc);
+
+    let max_uploads: Option<i64> = row.get("max_uploads");
+    let uploads_served: i64 = row.get("uploads_served");
+
+    let is_expired = expires_at < now || max_uploads.is_some_and(|max| uploads_served >= max);
+
+    let allowed_content_types: Option<String> = row.get("allowed_content_types");
+    let allowed_content_types = allowed_content_types
+        .map(|value| value.split(',').map(str::to_string).collect())
+        .unwrap_or_default();
+
+    Ok(UploadLink {
+        id: row.get("id"),
+        bucket: row.get("bucket"),
+        endpoint: row.get("endpoint"),
+        prefix: row.get("prefix"),
+        expires_at,
+        max_uploads,
+        uploads_served,
+        max_file_size: row.get("max_file_size"),
+        allowed_content_types,
+        created_at,
+        is_expired,
+    })
+}
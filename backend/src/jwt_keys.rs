@@ -0,0 +1,467 @@
+use std::collections::HashMap;
+
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, decode_header,
+};
+use rsa::pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey};
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use rsa::traits::PublicKeyParts;
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::config::AppConfig;
+
+#[derive(Debug, Error)]
+pub enum KeyError {
+    #[error("Failed to read key file {0}: {1}")]
+    Io(String, std::io::Error),
+    #[error("Key file {0} is not an RSA or Ed25519 PEM key")]
+    UnsupportedKey(String),
+    #[error("JWT_ALGORITHM {0:?} is not supported (use HS256, RS256 or EdDSA)")]
+    UnsupportedAlgorithm(Algorithm),
+    #[error("Signing key {0} does not match JWT_ALGORITHM {1:?}")]
+    AlgorithmMismatch(String, Algorithm),
+    #[error("Invalid key material: {0}")]
+    InvalidKey(String),
+}
+
+/// Public half of an asymmetric key, kept in JWK form
+#[derive(Debug, Clone)]
+enum PublicKey {
+    Rsa { n: String, e: String },
+    Ed25519 { x: String },
+}
+
+impl PublicKey {
+    fn from_rsa(key: &RsaPublicKey) -> Self {
+        PublicKey::Rsa {
+            n: URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+            e: URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+        }
+    }
+
+    fn from_ed25519(key: &VerifyingKey) -> Self {
+        PublicKey::Ed25519 {
+            x: URL_SAFE_NO_PAD.encode(key.to_bytes()),
+        }
+    }
+
+    fn algorithm(&self) -> Algorithm {
+        match self {
+            PublicKey::Rsa { .. } => Algorithm::RS256,
+            PublicKey::Ed25519 { .. } => Algorithm::EdDSA,
+        }
+    }
+
+    fn decoding_key(&self) -> Result<DecodingKey, KeyError> {
+        match self {
+            PublicKey::Rsa { n, e } => DecodingKey::from_rsa_components(n, e)
+                .map_err(|e| KeyError::InvalidKey(e.to_string())),
+            PublicKey::Ed25519 { x } => {
+                DecodingKey::from_ed_components(x).map_err(|e| KeyError::InvalidKey(e.to_string()))
+            }
+        }
+    }
+
+    /// RFC 7638 thumbprint, used as the `kid`
+    fn thumbprint(&self) -> String {
+        // Members must be in lexicographic order with no whitespace
+        let canonical = match self {
+            PublicKey::Rsa { n, e } => format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, e, n),
+            PublicKey::Ed25519 { x } => {
+                format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, x)
+            }
+        };
+        URL_SAFE_NO_PAD.encode(Sha256::digest(canonical.as_bytes()))
+    }
+
+    fn to_jwk(&self, kid: &str) -> Jwk {
+        match self {
+            PublicKey::Rsa { n, e } => Jwk {
+                kty: "RSA",
+                alg: "RS256",
+                kid: kid.to_string(),
+                key_use: "sig",
+                n: Some(n.clone()),
+                e: Some(e.clone()),
+                crv: None,
+                x: None,
+            },
+            PublicKey::Ed25519 { x } => Jwk {
+                kty: "OKP",
+                alg: "EdDSA",
+                kid: kid.to_string(),
+                key_use: "sig",
+                n: None,
+                e: None,
+                crv: Some("Ed25519"),
+                x: Some(x.clone()),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Jwk {
+    pub kty: &'static str,
+    pub alg: &'static str,
+    pub kid: String,
+    #[serde(rename = "use")]
+    pub key_use: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+struct VerificationKey {
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+/// The `JWT_*` settings keys are loaded from
+struct KeySettings<'a> {
+    algorithm: Algorithm,
+    secret: Option<&'a str>,
+    signing_key_path: Option<&'a str>,
+    verification_key_paths: &'a [String],
+    legacy_accept_until: Option<DateTime<Utc>>,
+}
+
+/// Keys used to issue and verify session tokens.
+///
+/// Rotation: generate a new key, point `JWT_SIGNING_KEY_PATH` at it and add the
+/// previous key file to `JWT_VERIFICATION_KEY_PATHS`. Tokens signed with the old
+/// key keep verifying (and the key stays in the JWKS) until it is removed from the
+/// list, which is safe once `JWT_EXP_MINUTES` have passed.
+///
+/// Moving from HS256 to a key pair: set `JWT_LEGACY_ACCEPT_UNTIL` to
+/// `JWT_EXP_MINUTES` after the switch so tokens already issued with `JWT_SECRET`
+/// run out, then remove both settings.
+pub struct JwtKeys {
+    header: Header,
+    encoding_key: EncodingKey,
+    verification_keys: HashMap<String, VerificationKey>,
+    // HS256 tokens carry no kid. They verify against JWT_SECRET while it signs, or
+    // until `legacy_accept_until` once a key pair has taken over.
+    legacy_secret: Option<DecodingKey>,
+    legacy_accept_until: Option<DateTime<Utc>>,
+    jwks: JwkSet,
+}
+
+impl JwtKeys {
+    pub fn from_config(config: &AppConfig) -> Result<Self, KeyError> {
+        Self::load(&KeySettings {
+            algorithm: config.jwt_algorithm,
+            secret: config.jwt_secret.as_deref(),
+            signing_key_path: config.jwt_signing_key_path.as_deref(),
+            verification_key_paths: &config.jwt_verification_key_paths,
+            legacy_accept_until: config.jwt_legacy_accept_until,
+        })
+    }
+
+    fn load(settings: &KeySettings) -> Result<Self, KeyError> {
+        let mut verification_keys = HashMap::new();
+        let mut jwks = JwkSet { keys: Vec::new() };
+
+        let (header, encoding_key) = match settings.algorithm {
+            Algorithm::HS256 => {
+                let secret = settings.secret.unwrap_or_default();
+                (
+                    Header::new(Algorithm::HS256),
+                    EncodingKey::from_secret(secret.as_bytes()),
+                )
+            }
+            Algorithm::RS256 | Algorithm::EdDSA => {
+                let path = settings.signing_key_path.unwrap_or_default();
+                let pem = read_key_file(path)?;
+                let public_key = parse_private_key(path, &pem)?;
+                if public_key.algorithm() != settings.algorithm {
+                    return Err(KeyError::AlgorithmMismatch(
+                        path.to_string(),
+                        settings.algorithm,
+                    ));
+                }
+
+                let encoding_key = match public_key {
+                    PublicKey::Rsa { .. } => EncodingKey::from_rsa_pem(pem.as_bytes()),
+                    PublicKey::Ed25519 { .. } => EncodingKey::from_ed_pem(pem.as_bytes()),
+                }
+                .map_err(|e| KeyError::InvalidKey(e.to_string()))?;
+
+                // The kid is derived from the key so it stays stable after rotation
+                let kid = public_key.thumbprint();
+                add_verification_key(&mut verification_keys, &mut jwks, &kid, &public_key)?;
+
+                let mut header = Header::new(settings.algorithm);
+                header.kid = Some(kid);
+                (header, encoding_key)
+            }
+            other => return Err(KeyError::UnsupportedAlgorithm(other)),
+        };
+
+        // Retired keys: still accepted, no longer used for signing
+        for path in settings.verification_key_paths {
+            let pem = read_key_file(path)?;
+            let public_key = parse_public_key(path, &pem)?;
+            let kid = public_key.thumbprint();
+            add_verification_key(&mut verification_keys, &mut jwks, &kid, &public_key)?;
+        }
+
+        let legacy_secret = match (settings.algorithm, settings.legacy_accept_until) {
+            (Algorithm::HS256, _) | (_, Some(_)) => settings.secret,
+            _ => None,
+        };
+
+        Ok(Self {
+            header,
+            encoding_key,
+            verification_keys,
+            legacy_secret: legacy_secret.map(|secret| DecodingKey::from_secret(secret.as_bytes())),
+            legacy_accept_until: settings
+                .legacy_accept_until
+                .filter(|_| settings.algorithm != Algorithm::HS256),
+            jwks,
+        })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn encoding_key(&self) -> &EncodingKey {
+        &self.encoding_key
+    }
+
+    /// Claims of a token signed with one of our keys, if it is valid and unexpired
+    pub fn verify<T: DeserializeOwned>(&self, token: &str) -> Option<T> {
+        // The header is untrusted; it only selects which of our keys to try
+        let header = decode_header(token).ok()?;
+        let key = self.verification_key(header.kid.as_deref(), header.alg)?;
+        decode::<T>(token, &key.key, &Validation::new(key.algorithm))
+            .ok()
+            .map(|data| data.claims)
+    }
+
+    /// Pick the key a token claims to be signed with
+    fn verification_key(&self, kid: Option<&str>, alg: Algorithm) -> Option<VerificationKey> {
+        match kid {
+            Some(kid) => self
+                .verification_keys
+                .get(kid)
+                .filter(|key| key.algorithm == alg)
+                .map(|key| VerificationKey {
+                    algorithm: key.algorithm,
+                    key: key.key.clone(),
+                }),
+            None if alg == Algorithm::HS256 => self
+                .legacy_secret
+                .clone()
+                .filter(|_| {
+                    self.legacy_accept_until
+                        .is_none_or(|until| Utc::now() < until)
+                })
+                .map(|key| VerificationKey {
+                    algorithm: Algorithm::HS256,
+                    key,
+                }),
+            None => None,
+        }
+    }
+
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+}
+
+fn add_verification_key(
+    verification_keys: &mut HashMap<String, VerificationKey>,
+    jwks: &mut JwkSet,
+    kid: &str,
+    public_key: &PublicKey,
+) -> Result<(), KeyError> {
+    if verification_keys.contains_key(kid) {
+        return Ok(());
+    }
+
+    verification_keys.insert(
+        kid.to_string(),
+        VerificationKey {
+            algorithm: public_key.algorithm(),
+            key: public_key.decoding_key()?,
+        },
+    );
+    jwks.keys.push(public_key.to_jwk(kid));
+    Ok(())
+}
+
+fn read_key_file(path: &str) -> Result<String, KeyError> {
+    std::fs::read_to_string(path).map_err(|e| KeyError::Io(path.to_string(), e))
+}
+
+fn parse_private_key(path: &str, pem: &str) -> Result<PublicKey, KeyError> {
+    if let Ok(key) = RsaPrivateKey::from_pkcs8_pem(pem) {
+        return Ok(PublicKey::from_rsa(&key.to_public_key()));
+    }
+    if let Ok(key) = RsaPrivateKey::from_pkcs1_pem(pem) {
+        return Ok(PublicKey::from_rsa(&key.to_public_key()));
+    }
+    if let Ok(key) = SigningKey::from_pkcs8_pem(pem) {
+        return Ok(PublicKey::from_ed25519(&key.verifying_key()));
+    }
+    Err(KeyError::UnsupportedKey(path.to_string()))
+}
+
+// Accepts public keys, or the old private key file itself
+fn parse_public_key(path: &str, pem: &str) -> Result<PublicKey, KeyError> {
+    if let Ok(key) = RsaPublicKey::from_public_key_pem(pem) {
+        return Ok(PublicKey::from_rsa(&key));
+    }
+    if let Ok(key) = RsaPublicKey::from_pkcs1_pem(pem) {
+        return Ok(PublicKey::from_rsa(&key));
+    }
+    if let Ok(key) = VerifyingKey::from_public_key_pem(pem) {
+        return Ok(PublicKey::from_ed25519(&key));
+    }
+    parse_private_key(path, pem)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use ed25519_dalek::pkcs8::EncodePrivateKey;
+    use ed25519_dalek::pkcs8::spki::der::pem::LineEnding;
+    use jsonwebtoken::encode;
+    use serde_json::{Value, json};
+
+    use super::*;
+
+    const SECRET: &str = "legacy-secret";
+
+    /// A fresh Ed25519 key written to a PEM file that is removed on drop
+    struct KeyFile(std::path::PathBuf);
+
+    impl KeyFile {
+        fn generate() -> Self {
+            let key = SigningKey::from_bytes(&rand::random());
+            let path = std::env::temp_dir().join(format!("jwt-key-{}.pem", uuid::Uuid::new_v4()));
+            std::fs::write(&path, key.to_pkcs8_pem(LineEnding::LF).unwrap().as_bytes()).unwrap();
+            Self(path)
+        }
+
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for KeyFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn eddsa_keys(
+        signing: &KeyFile,
+        retired: &[&KeyFile],
+        legacy_accept_until: Option<DateTime<Utc>>,
+    ) -> JwtKeys {
+        let verification_key_paths: Vec<String> =
+            retired.iter().map(|key| key.path().to_string()).collect();
+        JwtKeys::load(&KeySettings {
+            algorithm: Algorithm::EdDSA,
+            secret: Some(SECRET),
+            signing_key_path: Some(signing.path()),
+            verification_key_paths: &verification_key_paths,
+            legacy_accept_until,
+        })
+        .unwrap()
+    }
+
+    fn claims() -> Value {
+        let now = Utc::now().timestamp();
+        json!({ "sub": "alice", "iat": now, "exp": now + 600 })
+    }
+
+    fn sign(keys: &JwtKeys) -> String {
+        encode(keys.header(), &claims(), keys.encoding_key()).unwrap()
+    }
+
+    fn legacy_token() -> String {
+        encode(
+            &Header::new(Algorithm::HS256),
+            &claims(),
+            &EncodingKey::from_secret(SECRET.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    fn accepts(keys: &JwtKeys, token: &str) -> bool {
+        keys.verify::<Value>(token).is_some()
+    }
+
+    #[test]
+    fn rejects_a_kid_used_with_another_algorithm() {
+        let key = KeyFile::generate();
+        let keys = eddsa_keys(&key, &[], None);
+        assert!(accepts(&keys, &sign(&keys)));
+
+        // Our kid with HS256 keyed by the public key, the classic algorithm confusion
+        let jwk = &keys.jwks().keys[0];
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some(jwk.kid.clone());
+        let public_key = URL_SAFE_NO_PAD.decode(jwk.x.as_ref().unwrap()).unwrap();
+        let forged = encode(&header, &claims(), &EncodingKey::from_secret(&public_key)).unwrap();
+        assert!(!accepts(&keys, &forged));
+    }
+
+    #[test]
+    fn rejects_an_unknown_kid() {
+        let ours = KeyFile::generate();
+        let theirs = KeyFile::generate();
+        let keys = eddsa_keys(&ours, &[], None);
+        let foreign = eddsa_keys(&theirs, &[], None);
+        assert!(!accepts(&keys, &sign(&foreign)));
+    }
+
+    #[test]
+    fn accepts_tokens_from_a_rotated_out_key() {
+        let old = KeyFile::generate();
+        let new = KeyFile::generate();
+        let token = sign(&eddsa_keys(&old, &[], None));
+
+        let rotated = eddsa_keys(&new, &[&old], None);
+        assert!(accepts(&rotated, &token));
+        assert!(accepts(&rotated, &sign(&rotated)));
+        assert_eq!(rotated.jwks().keys.len(), 2);
+
+        // Dropping the old key from the list ends it
+        assert!(!accepts(&eddsa_keys(&new, &[], None), &token));
+    }
+
+    #[test]
+    fn accepts_hs256_only_until_the_legacy_deadline() {
+        let key = KeyFile::generate();
+        let token = legacy_token();
+
+        assert!(!accepts(&eddsa_keys(&key, &[], None), &token));
+        let open = eddsa_keys(&key, &[], Some(Utc::now() + Duration::hours(1)));
+        assert!(accepts(&open, &token));
+        let closed = eddsa_keys(&key, &[], Some(Utc::now() - Duration::seconds(1)));
+        assert!(!accepts(&closed, &token));
+    }
+}
@@ -0,0 +1,101 @@
+use chrono::{DateTime, Utc};
+
+use crate::oss_client::ObjectMetadata;
+
+/// Everything shown on the interstitial page in front of a download
+pub struct LandingPage<'a> {
+    pub file_name: &'a str,
+    pub metadata: Option<&'a ObjectMetadata>,
+    pub expires_at: DateTime<Utc>,
+    pub remaining_downloads: Option<u32>,
+}
+
+impl LandingPage<'_> {
+    pub fn render(&self) -> String {
+        let size = self
+            .metadata
+            .map(|metadata| format_size(metadata.size))
+            .unwrap_or_else(|| "Unknown".to_string());
+        let last_modified = self
+            .metadata
+            .and_then(|metadata| metadata.last_modified.as_deref())
+            .unwrap_or("Unknown");
+        let remaining = self
+            .remaining_downloads
+            .map(|count| count.to_string())
+            .unwrap_or_else(|| "Unlimited".to_string());
+
+        // The button posts back to this same URL; link-preview bots only ever GET,
+        // so they see this page without consuming a download
+        format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta name="robots" content="noindex, nofollow">
+<title>{name}</title>
+<style>
+body {{ font-family: system-ui, sans-serif; background: #f5f5f5; margin: 0; }}
+main {{ max-width: 28rem; margin: 10vh auto; background: #fff; padding: 2rem; border-radius: 8px; box-shadow: 0 1px 4px rgba(0, 0, 0, .1); }}
+h1 {{ font-size: 1.25rem; word-break: break-all; margin-top: 0; }}
+dl {{ display: grid; grid-template-columns: auto 1fr; gap: .5rem 1rem; }}
+dt {{ color: #666; }}
+dd {{ margin: 0; }}
+button {{ width: 100%; padding: .75rem; font-size: 1rem; border: 0; border-radius: 4px; background: #1677ff; color: #fff; cursor: pointer; }}
+</style>
+</head>
+<body>
+<main>
+<h1>{name}</h1>
+<dl>
+<dt>Size</dt><dd>{size}</dd>
+<dt>Last modified</dt><dd>{last_modified}</dd>
+<dt>Link expires</dt><dd>{expires}</dd>
+<dt>Downloads left</dt><dd>{remaining}</dd>
+</dl>
+<form method="post">
+<button type="submit">Download</button>
+</form>
+</main>
+</body>
+</html>
+"#,
+            name = escape_html(self.file_name),
+            size = size,
+            last_modified = escape_html(last_modified),
+            expires = self.expires_at.format("%Y-%m-%d %H:%M UTC"),
+            remaining = remaining,
+        )
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,69 @@
+use std::str::FromStr;
+
+use rand::Rng;
+use uuid::Uuid;
+
+const BASE62_ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+// 22 base62 characters carry just over 128 bits, the same entropy as a UUID v4
+pub const BASE62_FULL_LENGTH: usize = 22;
+// Below this the ID space is small enough to be enumerated
+const BASE62_MIN_LENGTH: usize = 8;
+
+/// How IDs for new links are generated. Existing links keep whatever ID they were
+/// created with, so switching formats never breaks published URLs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkIdFormat {
+    Uuid,
+    Base62 { length: usize },
+}
+
+impl LinkIdFormat {
+    pub fn generate(&self) -> String {
+        match self {
+            LinkIdFormat::Uuid => Uuid::new_v4().to_string(),
+            LinkIdFormat::Base62 { length } => {
+                let mut rng = rand::thread_rng();
+                (0..*length)
+                    .map(|_| BASE62_ALPHABET[rng.gen_range(0..BASE62_ALPHABET.len())] as char)
+                    .collect()
+            }
+        }
+    }
+
+    /// Short IDs can realistically collide and must be checked against existing links
+    pub fn needs_collision_check(&self) -> bool {
+        matches!(self, LinkIdFormat::Base62 { length } if *length < BASE62_FULL_LENGTH)
+    }
+}
+
+impl FromStr for LinkIdFormat {
+    type Err = String;
+
+    /// Accepts `uuid`, `base62` (128-bit) or `base62:<length>`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim().to_ascii_lowercase();
+        match value.split_once(':') {
+            None if value == "uuid" => Ok(LinkIdFormat::Uuid),
+            None if value == "base62" => Ok(LinkIdFormat::Base62 {
+                length: BASE62_FULL_LENGTH,
+            }),
+            Some(("base62", length)) => {
+                let length: usize = length
+                    .parse()
+                    .map_err(|_| format!("invalid length {:?}", length))?;
+                if !(BASE62_MIN_LENGTH..=64).contains(&length) {
+                    return Err(format!(
+                        "base62 length must be between {} and 64",
+                        BASE62_MIN_LENGTH
+                    ));
+                }
+                Ok(LinkIdFormat::Base62 { length })
+            }
+            _ => Err(format!(
+                "expected uuid, base62 or base62:<length>, got {:?}",
+                value
+            )),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::str::FromStr;
+
+use axum::http::{HeaderMap, header};
+
+/// User-Agent fragments of chat apps and social sites that fetch URLs to unfurl them
+pub const DEFAULT_PREVIEW_USER_AGENTS: &[&str] = &[
+    "slackbot",
+    "slack-imgproxy",
+    "skypeuripreview",
+    "microsoftpreview",
+    "teamsbot",
+    "discordbot",
+    "telegrambot",
+    "whatsapp",
+    "facebookexternalhit",
+    "twitterbot",
+    "linkedinbot",
+    "mattermost-bot",
+    "redditbot",
+    "iframely",
+    "embedly",
+];
+
+/// What to do when a known link-preview client requests a download
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PreviewAction {
+    /// Answer with the landing page, without counting or revealing the signed URL
+    Ignore,
+    /// Refuse with 403
+    Deny,
+}
+
+impl FromStr for PreviewAction {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "ignore" => Ok(PreviewAction::Ignore),
+            "deny" => Ok(PreviewAction::Deny),
+            other => Err(format!("expected ignore or deny, got {:?}", other)),
+        }
+    }
+}
+
+/// `patterns` must already be lowercase
+pub fn is_preview_client(headers: &HeaderMap, patterns: &[String]) -> bool {
+    let Some(user_agent) = headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+
+    let user_agent = user_agent.to_ascii_lowercase();
+    patterns
+        .iter()
+        .any(|pattern| user_agent.contains(pattern.as_str()))
+}
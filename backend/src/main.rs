@@ -1,19 +1,37 @@
 mod auth;
+mod cdn;
+mod client_ip;
 mod config;
+mod content_disposition;
 mod database;
+mod jwt_keys;
+mod landing_page;
+mod link_id;
+mod link_preview;
+mod metrics;
 mod oauth;
+mod object_search;
+mod oidc;
 mod oss_client;
+mod qr;
+mod rate_limit;
 mod routes;
 mod state;
+mod watermark;
 
 use std::net::SocketAddr;
 
 use axum::Router;
+use axum::http::{Method, header};
 use config::AppConfig;
 use dotenvy::dotenv;
-use tower_http::cors::{AllowMethods, AllowOrigin, CorsLayer};
+use tower_http::cors::{AllowOrigin, CorsLayer};
 
 use crate::database::Database;
+use crate::jwt_keys::JwtKeys;
+use crate::oidc::OidcProvider;
+use crate::oss_client::{OssClient, OssError};
+use crate::rate_limit::{MemoryStore, RateLimitStore, RateLimiter};
 use crate::state::AppState;
 
 #[tokio::main]
@@ -49,7 +67,39 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
 
     let database = Database::new(&database_url).await?;
 
-    let state = AppState::new(config, database);
+    let jwt_keys = JwtKeys::from_config(&config)?;
+
+    let oidc = match config.oauth_issuer_url.as_deref() {
+        Some(issuer) => Some(
+            OidcProvider::discover(
+                issuer,
+                &config.oauth_client_id,
+                config.oauth_admin_role.clone(),
+            )
+            .await?,
+        ),
+        None => None,
+    };
+
+    let rate_limiter = RateLimiter::new(
+        build_rate_limit_store(&config).await?,
+        config.rate_limit_download_per_ip,
+        config.rate_limit_download_per_link,
+        config.rate_limit_auth_per_ip,
+    );
+
+    // Without a default endpoint only the OSS-backed management routes are unavailable
+    let oss = match OssClient::new(&config) {
+        Ok(client) => Some(client),
+        Err(OssError::MissingEndpoint) => None,
+        Err(err) => return Err(err.into()),
+    };
+
+    let state = AppState::new(config, database, jwt_keys, oidc, rate_limiter, oss);
+    tokio::spawn(routes::sweep_upload_sessions(state.clone()));
+    if state.config.allows_any_origin() {
+        eprintln!("OAuth login is disabled until CORS_ALLOWED_ORIGINS lists the frontend origins");
+    }
     let cors = build_cors_layer(state.config.as_ref());
 
     let app: Router = routes::create_router(state).layer(cors);
@@ -58,12 +108,29 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
     let listener = tokio::net::TcpListener::bind(addr).await?;
     println!("Server running on http://{}:{}", api_host, api_port);
 
-    axum::serve(listener, app).await?;
+    // Peer addresses are needed to resolve the client IP for network restrictions
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
     Ok(())
 }
 
+async fn build_rate_limit_store(
+    config: &AppConfig,
+) -> Result<Box<dyn RateLimitStore>, Box<dyn std::error::Error>> {
+    match config.rate_limit_redis_url.as_deref() {
+        #[cfg(feature = "redis")]
+        Some(url) => Ok(Box::new(rate_limit::RedisStore::connect(url).await?)),
+        #[cfg(not(feature = "redis"))]
+        Some(_) => Err("RATE_LIMIT_REDIS_URL is set but the `redis` feature is not enabled".into()),
+        None => Ok(Box::new(MemoryStore::new())),
+    }
+}
+
 fn build_cors_layer(config: &AppConfig) -> CorsLayer {
-    if config.cors_allowed_origins.len() == 1 && config.cors_allowed_origins[0] == "*" {
+    if config.allows_any_origin() {
         CorsLayer::permissive()
     } else {
         let origins: Vec<_> = config
@@ -72,10 +139,12 @@ fn build_cors_layer(config: &AppConfig) -> CorsLayer {
             .filter_map(|origin| origin.parse().ok())
             .collect();
 
+        // Credentials let the OAuth state cookie reach the callback from these origins
         let allow_origin = AllowOrigin::list(origins);
         CorsLayer::new()
             .allow_origin(allow_origin)
-            .allow_methods(AllowMethods::any())
-            .allow_headers(tower_http::cors::AllowHeaders::any())
+            .allow_methods([Method::GET, Method::HEAD, Method::POST, Method::DELETE])
+            .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+            .allow_credentials(true)
     }
 }
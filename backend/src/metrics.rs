@@ -0,0 +1,354 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::oss_client::{CacheStats, OperationStats};
+use crate::state::AppState;
+
+/// Upper bounds, in seconds, of the latency histogram buckets (Prometheus client defaults)
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct Histogram {
+    // Not cumulative; summed up when rendered
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    pub fn observe(&mut self, seconds: f64) {
+        if let Some(index) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[index] += 1;
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+
+    /// `labels` is either empty or a comma-terminated `name="value",` list
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(self.buckets) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{}le=\"{}\"}} {}",
+                name, labels, bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{}le=\"+Inf\"}} {}",
+            name, labels, self.count
+        );
+        let labels = labels.trim_end_matches(',');
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+/// Why a download request was turned away
+pub fn denial_reason(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::NOT_FOUND => "not_found",
+        StatusCode::GONE => "expired",
+        StatusCode::TOO_MANY_REQUESTS => "limit",
+        StatusCode::FORBIDDEN => "forbidden",
+        _ => "error",
+    }
+}
+
+/// Application counters; OSS call statistics live in `OssClient`
+#[derive(Default)]
+pub struct Metrics {
+    links_created: AtomicU64,
+    downloads_served: AtomicU64,
+    downloads_denied: Mutex<BTreeMap<&'static str, u64>>,
+    oauth_callbacks: Mutex<BTreeMap<&'static str, u64>>,
+    http_requests: Mutex<BTreeMap<HttpRoute, RouteStats>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct HttpRoute {
+    method: String,
+    route: String,
+}
+
+#[derive(Default)]
+struct RouteStats {
+    statuses: BTreeMap<u16, u64>,
+    latency: Histogram,
+}
+
+/// Values read from elsewhere in the process at scrape time
+pub struct Snapshot {
+    pub tickets: usize,
+    pub oss_operations: BTreeMap<&'static str, OperationStats>,
+    pub bucket_cache: Option<CacheStats>,
+}
+
+impl Metrics {
+    pub fn link_created(&self) {
+        self.links_created.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn download_served(&self) {
+        self.downloads_served.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn download_denied(&self, reason: &'static str) {
+        increment(&self.downloads_denied, reason);
+    }
+
+    pub fn oauth_callback(&self, outcome: &'static str) {
+        increment(&self.oauth_callbacks, outcome);
+    }
+
+    fn observe_request(&self, method: &str, route: &str, status: u16, seconds: f64) {
+        let mut requests = self.http_requests.lock().unwrap_or_else(|e| e.into_inner());
+        let stats = requests
+            .entry(HttpRoute {
+                method: method.to_string(),
+                route: route.to_string(),
+            })
+            .or_default();
+        *stats.statuses.entry(status).or_default() += 1;
+        stats.latency.observe(seconds);
+    }
+
+    /// Prometheus text exposition format, version 0.0.4
+    pub fn render(&self, snapshot: &Snapshot) -> String {
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "links_created_total",
+            "counter",
+            "Download links created",
+        );
+        let _ = writeln!(
+            out,
+            "links_created_total {}",
+            self.links_created.load(Ordering::Relaxed)
+        );
+
+        header(
+            &mut out,
+            "downloads_served_total",
+            "counter",
+            "Downloads redirected or streamed",
+        );
+        let _ = writeln!(
+            out,
+            "downloads_served_total {}",
+            self.downloads_served.load(Ordering::Relaxed)
+        );
+
+        header(
+            &mut out,
+            "downloads_denied_total",
+            "counter",
+            "Download requests refused, by reason",
+        );
+        for (reason, count) in self
+            .downloads_denied
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+        {
+            let _ = writeln!(
+                out,
+                "downloads_denied_total{{reason=\"{}\"}} {}",
+                reason, count
+            );
+        }
+
+        header(
+            &mut out,
+            "oauth_callbacks_total",
+            "counter",
+            "OAuth callbacks, by outcome",
+        );
+        for (outcome, count) in self
+            .oauth_callbacks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+        {
+            let _ = writeln!(
+                out,
+                "oauth_callbacks_total{{outcome=\"{}\"}} {}",
+                outcome, count
+            );
+        }
+
+        header(
+            &mut out,
+            "download_tickets",
+            "gauge",
+            "Download links held in memory",
+        );
+        let _ = writeln!(out, "download_tickets {}", snapshot.tickets);
+
+        header(
+            &mut out,
+            "oss_request_duration_seconds",
+            "histogram",
+            "OSS API call latency, by operation",
+        );
+        for (operation, stats) in &snapshot.oss_operations {
+            let labels = format!("operation=\"{}\",", operation);
+            stats
+                .latency
+                .render(&mut out, "oss_request_duration_seconds", &labels);
+        }
+        header(
+            &mut out,
+            "oss_request_errors_total",
+            "counter",
+            "OSS API calls that failed or returned an error status other than 404",
+        );
+        for (operation, stats) in &snapshot.oss_operations {
+            let _ = writeln!(
+                out,
+                "oss_request_errors_total{{operation=\"{}\"}} {}",
+                operation, stats.errors
+            );
+        }
+
+        if let Some(cache) = &snapshot.bucket_cache {
+            header(
+                &mut out,
+                "oss_bucket_cache_hits_total",
+                "counter",
+                "Bucket region lookups answered from the cache",
+            );
+            let _ = writeln!(out, "oss_bucket_cache_hits_total {}", cache.hits);
+            header(
+                &mut out,
+                "oss_bucket_cache_misses_total",
+                "counter",
+                "Bucket region lookups that asked OSS",
+            );
+            let _ = writeln!(out, "oss_bucket_cache_misses_total {}", cache.misses);
+            header(
+                &mut out,
+                "oss_bucket_cache_entries",
+                "gauge",
+                "Bucket regions currently cached",
+            );
+            let _ = writeln!(out, "oss_bucket_cache_entries {}", cache.entries);
+        }
+
+        let requests = self.http_requests.lock().unwrap_or_else(|e| e.into_inner());
+        header(
+            &mut out,
+            "http_requests_total",
+            "counter",
+            "HTTP requests, by route and status",
+        );
+        for (key, stats) in requests.iter() {
+            for (status, count) in &stats.statuses {
+                let _ = writeln!(
+                    out,
+                    "http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                    key.method,
+                    escape_label(&key.route),
+                    status,
+                    count
+                );
+            }
+        }
+        header(
+            &mut out,
+            "http_request_duration_seconds",
+            "histogram",
+            "HTTP request latency, by route",
+        );
+        for (key, stats) in requests.iter() {
+            let labels = format!(
+                "method=\"{}\",route=\"{}\",",
+                key.method,
+                escape_label(&key.route)
+            );
+            stats
+                .latency
+                .render(&mut out, "http_request_duration_seconds", &labels);
+        }
+
+        out
+    }
+}
+
+/// Time every routed request. Routes are labelled by their pattern (`/download/:id`),
+/// never the raw path, so link IDs cannot blow up the series count.
+pub async fn track_requests(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(route) = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+    else {
+        return next.run(request).await;
+    };
+    let method = request.method().to_string();
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+    state.metrics.observe_request(
+        &method,
+        &route,
+        response.status().as_u16(),
+        started.elapsed().as_secs_f64(),
+    );
+    response
+}
+
+/// Whether the request carries `Authorization: Bearer <token>`, compared in constant time
+pub fn authorized(headers: &HeaderMap, token: &str) -> bool {
+    let Some(presented) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+
+    presented.len() == token.len()
+        && presented
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn increment(counters: &Mutex<BTreeMap<&'static str, u64>>, label: &'static str) {
+    let mut counters = counters.lock().unwrap_or_else(|e| e.into_inner());
+    *counters.entry(label).or_default() += 1;
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
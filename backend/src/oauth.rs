@@ -1,9 +1,33 @@
 use std::collections::HashMap;
 
+use axum::http::{HeaderMap, header};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use crate::config::AppConfig;
 
+// How long a user has to complete the login at the provider
+const OAUTH_SESSION_TTL_SECS: i64 = 600;
+// Binds the callback to the browser that started the login
+const STATE_COOKIE_NAME: &str = "oauth_state";
+const STATE_COOKIE_PATH: &str = "/api/oauth";
+
+/// Server-side half of an in-flight authorization request, keyed by `state`
+pub struct OAuthSession {
+    pub code_verifier: String,
+    pub nonce: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub struct LoginRequest {
+    pub state: String,
+    pub code_challenge: String,
+    pub session: OAuthSession,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenResponse {
     pub access_token: String,
@@ -11,6 +35,7 @@ pub struct TokenResponse {
     pub expires_in: Option<u64>,
     pub refresh_token: Option<String>,
     pub scope: Option<String>,
+    pub id_token: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -23,15 +48,15 @@ pub struct UserInfo {
 
 #[derive(Debug)]
 pub enum OAuthError {
-    #[allow(dead_code)]
     InvalidState,
-    #[allow(dead_code)]
     InvalidSession,
     TokenExchangeFailed(String),
     UserInfoFailed(String),
     PermissionDenied,
     #[allow(dead_code)]
     InvalidResponse(String),
+    DiscoveryFailed(String),
+    InvalidIdToken(String),
 }
 
 impl std::fmt::Display for OAuthError {
@@ -43,14 +68,109 @@ impl std::fmt::Display for OAuthError {
             OAuthError::UserInfoFailed(msg) => write!(f, "Failed to fetch user info: {}", msg),
             OAuthError::PermissionDenied => write!(f, "User does not have admin permission"),
             OAuthError::InvalidResponse(msg) => write!(f, "Invalid response: {}", msg),
+            OAuthError::DiscoveryFailed(msg) => write!(f, "OIDC discovery failed: {}", msg),
+            OAuthError::InvalidIdToken(msg) => write!(f, "Invalid id_token: {}", msg),
         }
     }
 }
 
 impl std::error::Error for OAuthError {}
 
+/// Generate state, nonce and a PKCE S256 pair for a new login
+pub fn new_login_request() -> LoginRequest {
+    let code_verifier = random_token(32);
+    let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+    LoginRequest {
+        state: random_token(24),
+        code_challenge,
+        session: OAuthSession {
+            code_verifier,
+            nonce: random_token(24),
+            expires_at: Utc::now() + Duration::seconds(OAUTH_SESSION_TTL_SECS),
+        },
+    }
+}
+
+pub fn build_authorize_url(
+    config: &AppConfig,
+    authorize_endpoint: &str,
+    login: &LoginRequest,
+) -> Result<String, OAuthError> {
+    let url = url::Url::parse_with_params(
+        authorize_endpoint,
+        &[
+            ("response_type", "code"),
+            ("client_id", config.oauth_client_id.as_str()),
+            ("redirect_uri", config.oauth_redirect_uri.as_str()),
+            ("scope", config.oauth_scope.as_str()),
+            ("state", login.state.as_str()),
+            ("nonce", login.session.nonce.as_str()),
+            ("code_challenge", login.code_challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .map_err(|e| OAuthError::InvalidResponse(format!("Invalid authorize URL: {}", e)))?;
+
+    Ok(url.to_string())
+}
+
+/// `Set-Cookie` value carrying a hash of `state`, so a callback is only accepted from
+/// the browser that started the login (login CSRF)
+pub fn state_cookie(state: &str, secure: bool) -> String {
+    format!(
+        "{}={}; Max-Age={}; Path={}; HttpOnly; SameSite=Lax{}",
+        STATE_COOKIE_NAME,
+        state_hash(state),
+        OAUTH_SESSION_TTL_SECS,
+        STATE_COOKIE_PATH,
+        if secure { "; Secure" } else { "" }
+    )
+}
+
+/// `Set-Cookie` value removing the state cookie once the callback has used it
+pub fn clear_state_cookie(secure: bool) -> String {
+    format!(
+        "{}=; Max-Age=0; Path={}; HttpOnly; SameSite=Lax{}",
+        STATE_COOKIE_NAME,
+        STATE_COOKIE_PATH,
+        if secure { "; Secure" } else { "" }
+    )
+}
+
+/// Whether any `Cookie` header holds the hash of `state`, compared in constant time
+pub fn state_cookie_matches(headers: &HeaderMap, state: &str) -> bool {
+    let expected = state_hash(state);
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .filter(|(name, _)| *name == STATE_COOKIE_NAME)
+        .any(|(_, value)| {
+            value.len() == expected.len()
+                && value
+                    .bytes()
+                    .zip(expected.bytes())
+                    .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+                    == 0
+        })
+}
+
+fn state_hash(state: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(state.as_bytes()))
+}
+
+fn random_token(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    rand::thread_rng().fill_bytes(&mut buf);
+    URL_SAFE_NO_PAD.encode(buf)
+}
+
 pub async fn exchange_code_for_token(
     config: &AppConfig,
+    token_url: &str,
     code: &str,
     code_verifier: &str,
 ) -> Result<TokenResponse, OAuthError> {
@@ -65,7 +185,7 @@ pub async fn exchange_code_for_token(
     params.insert("code_verifier", code_verifier);
 
     let response = client
-        .post(&config.oauth_token_url)
+        .post(token_url)
         .form(&params)
         .send()
         .await
@@ -116,13 +236,42 @@ pub async fn fetch_user_info(
 
 pub fn check_admin_permission(user_info: &UserInfo) -> Result<(), OAuthError> {
     // Check if user has admin_user permission set to true
-    if let Some(permissions) = &user_info.permissions {
-        if let Some(admin_user) = permissions.get("admin_user") {
-            if admin_user.as_bool() == Some(true) {
-                return Ok(());
-            }
-        }
+    if let Some(permissions) = &user_info.permissions
+        && let Some(admin_user) = permissions.get("admin_user")
+        && admin_user.as_bool() == Some(true)
+    {
+        return Ok(());
     }
 
     Err(OAuthError::PermissionDenied)
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn cookie_headers(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn state_cookie_binds_callback_to_login() {
+        let cookie = state_cookie("abc", true);
+        assert!(cookie.contains("HttpOnly; SameSite=Lax; Secure"));
+        assert!(!cookie.contains("abc"));
+
+        let pair = cookie.split(';').next().unwrap();
+        let headers = cookie_headers(&format!("theme=dark; {}", pair));
+        assert!(state_cookie_matches(&headers, "abc"));
+        assert!(!state_cookie_matches(&headers, "abd"));
+        assert!(!state_cookie_matches(&HeaderMap::new(), "abc"));
+        assert!(!state_cookie_matches(
+            &cookie_headers("oauth_state=abc"),
+            "abc"
+        ));
+    }
+}
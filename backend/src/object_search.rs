@@ -0,0 +1,143 @@
+use chrono::{DateTime, Utc};
+use globset::{Glob, GlobMatcher};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::oss_client::ObjectInfo;
+
+/// Query parameters for `GET /objects/search`
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub bucket: String,
+    pub prefix: Option<String>,
+    /// Shell-style pattern matched against the whole key; `*` also crosses `/`
+    pub glob: Option<String>,
+    /// Matched anywhere in the key unless anchored
+    pub regex: Option<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub modified_after: Option<DateTime<Utc>>,
+    pub modified_before: Option<DateTime<Utc>>,
+    /// Comma-separated, e.g. `Standard,IA`
+    pub storage_class: Option<String>,
+    /// Resume a search that stopped at its scan cap
+    #[serde(rename = "start-after")]
+    pub start_after: Option<String>,
+    /// Stop after this many keys have been examined; capped by the server limit
+    pub max_scan: Option<u64>,
+    /// Stop after this many matches
+    pub limit: Option<u64>,
+}
+
+/// Compiled form of the filters in a `SearchQuery`
+pub struct SearchFilter {
+    glob: Option<GlobMatcher>,
+    regex: Option<Regex>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    modified_after: Option<DateTime<Utc>>,
+    modified_before: Option<DateTime<Utc>>,
+    storage_classes: Vec<String>,
+}
+
+impl SearchFilter {
+    pub fn new(query: &SearchQuery) -> Result<Self, String> {
+        let glob = query
+            .glob
+            .as_deref()
+            .filter(|pattern| !pattern.is_empty())
+            .map(|pattern| {
+                Glob::new(pattern)
+                    .map(|glob| glob.compile_matcher())
+                    .map_err(|e| format!("Invalid glob: {}", e))
+            })
+            .transpose()?;
+        let regex = query
+            .regex
+            .as_deref()
+            .filter(|pattern| !pattern.is_empty())
+            .map(|pattern| Regex::new(pattern).map_err(|e| format!("Invalid regex: {}", e)))
+            .transpose()?;
+
+        if let (Some(min), Some(max)) = (query.min_size, query.max_size)
+            && min > max
+        {
+            return Err("min_size must not exceed max_size".to_string());
+        }
+
+        let storage_classes = query
+            .storage_class
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(|class| class.trim().to_ascii_lowercase())
+            .filter(|class| !class.is_empty())
+            .collect();
+
+        Ok(Self {
+            glob,
+            regex,
+            min_size: query.min_size,
+            max_size: query.max_size,
+            modified_after: query.modified_after,
+            modified_before: query.modified_before,
+            storage_classes,
+        })
+    }
+
+    pub fn matches(&self, object: &ObjectInfo) -> bool {
+        if self.min_size.is_some_and(|min| object.size < min)
+            || self.max_size.is_some_and(|max| object.size > max)
+        {
+            return false;
+        }
+
+        if !self.storage_classes.is_empty()
+            && !self
+                .storage_classes
+                .contains(&object.storage_class.to_ascii_lowercase())
+        {
+            return false;
+        }
+
+        if self.modified_after.is_some() || self.modified_before.is_some() {
+            // An unparseable timestamp cannot be shown to fall inside the range
+            let Ok(modified) = DateTime::parse_from_rfc3339(&object.last_modified) else {
+                return false;
+            };
+            if self.modified_after.is_some_and(|after| modified < after)
+                || self.modified_before.is_some_and(|before| modified > before)
+            {
+                return false;
+            }
+        }
+
+        self.glob
+            .as_ref()
+            .is_none_or(|glob| glob.is_match(&object.key))
+            && self
+                .regex
+                .as_ref()
+                .is_none_or(|regex| regex.is_match(&object.key))
+    }
+}
+
+/// One NDJSON line of search output
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SearchEvent {
+    Object(ObjectInfo),
+    /// Always the last line of a search that did not fail
+    Done {
+        scanned: u64,
+        matched: u64,
+        /// Stopped at the scan cap or match limit before reaching the end of the prefix
+        truncated: bool,
+        /// Pass as `start-after` to continue a truncated search
+        resume_after: Option<String>,
+    },
+    /// OSS failed part way through; earlier lines are still valid
+    Error {
+        message: String,
+    },
+}
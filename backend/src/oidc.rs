@@ -0,0 +1,494 @@
+use std::time::{Duration, Instant};
+
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::oauth::{OAuthError, UserInfo};
+
+// Keys are refreshed at least this often even if every kid is known
+const JWKS_CACHE_TTL: Duration = Duration::from_secs(3600);
+// Unknown kids trigger a refresh, but never more often than this
+const JWKS_MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    #[allow(dead_code)]
+    pub userinfo_endpoint: Option<String>,
+    pub jwks_uri: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub preferred_username: Option<String>,
+    pub email: Option<String>,
+    pub nonce: Option<String>,
+    pub permissions: Option<serde_json::Value>,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+struct CachedJwks {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+pub struct OidcProvider {
+    pub metadata: ProviderMetadata,
+    client_id: String,
+    admin_role: Option<String>,
+    jwks: RwLock<CachedJwks>,
+    client: reqwest::Client,
+}
+
+impl OidcProvider {
+    /// Fetch `.well-known/openid-configuration` and the initial key set for an issuer
+    pub async fn discover(
+        issuer_url: &str,
+        client_id: &str,
+        admin_role: Option<String>,
+    ) -> Result<Self, OAuthError> {
+        let client = reqwest::Client::new();
+        let issuer_url = issuer_url.trim_end_matches('/');
+        let discovery_url = format!("{}/.well-known/openid-configuration", issuer_url);
+
+        let response = client
+            .get(&discovery_url)
+            .send()
+            .await
+            .map_err(|e| OAuthError::DiscoveryFailed(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(OAuthError::DiscoveryFailed(format!(
+                "HTTP {} from {}",
+                response.status(),
+                discovery_url
+            )));
+        }
+
+        let metadata = response
+            .json::<ProviderMetadata>()
+            .await
+            .map_err(|e| OAuthError::DiscoveryFailed(e.to_string()))?;
+
+        // The issuer in the document must be the one we were configured with
+        if metadata.issuer.trim_end_matches('/') != issuer_url {
+            return Err(OAuthError::DiscoveryFailed(format!(
+                "Issuer mismatch: expected {}, got {}",
+                issuer_url, metadata.issuer
+            )));
+        }
+
+        let keys = fetch_jwks(&client, &metadata.jwks_uri).await?;
+
+        Ok(Self {
+            metadata,
+            client_id: client_id.to_string(),
+            admin_role,
+            jwks: RwLock::new(CachedJwks {
+                keys,
+                fetched_at: Instant::now(),
+            }),
+            client,
+        })
+    }
+
+    /// Verify signature, iss, aud, exp and nonce of an id_token and return its claims
+    pub async fn validate_id_token(
+        &self,
+        id_token: &str,
+        expected_nonce: Option<&str>,
+    ) -> Result<IdTokenClaims, OAuthError> {
+        let header =
+            decode_header(id_token).map_err(|e| OAuthError::InvalidIdToken(e.to_string()))?;
+
+        // Only accept asymmetric algorithms; HS* would let anyone holding the
+        // client secret mint tokens and opens the door to key confusion
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err(OAuthError::InvalidIdToken(format!(
+                "Unsupported signing algorithm {:?}",
+                header.alg
+            )));
+        }
+
+        let jwk = self.find_key(header.kid.as_deref()).await?;
+        let key = DecodingKey::from_jwk(&jwk)
+            .map_err(|e| OAuthError::InvalidIdToken(format!("Unusable signing key: {}", e)))?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.metadata.issuer]);
+        validation.set_audience(&[&self.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(|e| OAuthError::InvalidIdToken(e.to_string()))?
+            .claims;
+
+        match (expected_nonce, claims.nonce.as_deref()) {
+            (Some(expected), Some(actual)) if expected == actual => {}
+            (None, None) => {}
+            _ => return Err(OAuthError::InvalidIdToken("Nonce mismatch".to_string())),
+        }
+
+        Ok(claims)
+    }
+
+    /// Map id_token claims onto the user model used by the rest of the app
+    pub fn user_info_from_claims(&self, claims: IdTokenClaims) -> UserInfo {
+        let username = claims
+            .preferred_username
+            .clone()
+            .or_else(|| claims.email.clone())
+            .unwrap_or_else(|| claims.sub.clone());
+
+        // Providers without a `permissions` claim can grant admin through a role or group
+        let permissions = claims.permissions.or_else(|| {
+            self.admin_role.as_ref().and_then(|role| {
+                (claims.roles.contains(role) || claims.groups.contains(role))
+                    .then(|| serde_json::json!({ "admin_user": true }))
+            })
+        });
+
+        UserInfo {
+            sub: claims.sub,
+            username,
+            email: claims.email,
+            permissions,
+        }
+    }
+
+    async fn find_key(&self, kid: Option<&str>) -> Result<Jwk, OAuthError> {
+        {
+            let cached = self.jwks.read().await;
+            if cached.fetched_at.elapsed() < JWKS_CACHE_TTL
+                && let Some(jwk) = select_key(&cached.keys, kid)
+            {
+                return Ok(jwk.clone());
+            }
+        }
+
+        // Key not found or cache is stale: the provider may have rotated its keys
+        let mut cached = self.jwks.write().await;
+        if cached.fetched_at.elapsed() >= JWKS_MIN_REFRESH_INTERVAL {
+            match fetch_jwks(&self.client, &self.metadata.jwks_uri).await {
+                Ok(keys) => {
+                    cached.keys = keys;
+                    cached.fetched_at = Instant::now();
+                }
+                // Keep serving the previous keys if the provider is briefly unreachable
+                Err(err) if select_key(&cached.keys, kid).is_none() => return Err(err),
+                Err(_) => {}
+            }
+        }
+
+        select_key(&cached.keys, kid)
+            .cloned()
+            .ok_or_else(|| OAuthError::InvalidIdToken("No matching signing key".to_string()))
+    }
+}
+
+fn select_key<'a>(keys: &'a JwkSet, kid: Option<&str>) -> Option<&'a Jwk> {
+    match kid {
+        Some(kid) => keys.find(kid),
+        // Without a kid the token is only unambiguous if the provider has a single key
+        None if keys.keys.len() == 1 => keys.keys.first(),
+        None => None,
+    }
+}
+
+async fn fetch_jwks(client: &reqwest::Client, jwks_uri: &str) -> Result<JwkSet, OAuthError> {
+    let response = client
+        .get(jwks_uri)
+        .send()
+        .await
+        .map_err(|e| OAuthError::DiscoveryFailed(e.to_string()))?;
+
+    if !response.status().is_success() {
+        return Err(OAuthError::DiscoveryFailed(format!(
+            "HTTP {} from {}",
+            response.status(),
+            jwks_uri
+        )));
+    }
+
+    response
+        .json::<JwkSet>()
+        .await
+        .map_err(|e| OAuthError::DiscoveryFailed(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use axum::{Json, Router, extract::State, routing::get};
+    use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+    use ed25519_dalek::SigningKey;
+    use ed25519_dalek::pkcs8::EncodePrivateKey;
+    use jsonwebtoken::{EncodingKey, Header, encode};
+
+    use super::*;
+
+    const CLIENT_ID: &str = "dl-client";
+
+    struct TestKey {
+        kid: &'static str,
+        signing_key: SigningKey,
+    }
+
+    impl TestKey {
+        fn new(kid: &'static str) -> Self {
+            Self {
+                kid,
+                signing_key: SigningKey::from_bytes(&rand::random()),
+            }
+        }
+
+        fn jwk(&self) -> serde_json::Value {
+            serde_json::json!({
+                "kty": "OKP",
+                "crv": "Ed25519",
+                "alg": "EdDSA",
+                "use": "sig",
+                "kid": self.kid,
+                "x": URL_SAFE_NO_PAD.encode(self.signing_key.verifying_key().as_bytes()),
+            })
+        }
+
+        fn sign(&self, claims: &serde_json::Value) -> String {
+            let der = self.signing_key.to_pkcs8_der().unwrap();
+            let mut header = Header::new(Algorithm::EdDSA);
+            header.kid = Some(self.kid.to_string());
+            encode(&header, claims, &EncodingKey::from_ed_der(der.as_bytes())).unwrap()
+        }
+    }
+
+    #[derive(Clone)]
+    struct MockIdp {
+        issuer: Arc<Mutex<String>>,
+        jwks: Arc<Mutex<serde_json::Value>>,
+        jwks_fetches: Arc<AtomicUsize>,
+    }
+
+    impl MockIdp {
+        fn publish(&self, keys: &[&TestKey]) {
+            let keys: Vec<_> = keys.iter().map(|key| key.jwk()).collect();
+            *self.jwks.lock().unwrap() = serde_json::json!({ "keys": keys });
+        }
+
+        fn fetches(&self) -> usize {
+            self.jwks_fetches.load(Ordering::SeqCst)
+        }
+    }
+
+    /// Serve discovery and JWKS documents on an ephemeral port
+    async fn start_idp(keys: &[&TestKey]) -> (String, MockIdp) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        let idp = MockIdp {
+            issuer: Arc::new(Mutex::new(issuer.clone())),
+            jwks: Arc::new(Mutex::new(serde_json::Value::Null)),
+            jwks_fetches: Arc::new(AtomicUsize::new(0)),
+        };
+        idp.publish(keys);
+
+        let app = Router::new()
+            .route(
+                "/.well-known/openid-configuration",
+                get(|State(idp): State<MockIdp>| async move {
+                    let issuer = idp.issuer.lock().unwrap().clone();
+                    let base = issuer.trim_end_matches("/evil");
+                    Json(serde_json::json!({
+                        "issuer": issuer,
+                        "authorization_endpoint": format!("{}/authorize", base),
+                        "token_endpoint": format!("{}/token", base),
+                        "jwks_uri": format!("{}/jwks", base),
+                    }))
+                }),
+            )
+            .route(
+                "/jwks",
+                get(|State(idp): State<MockIdp>| async move {
+                    idp.jwks_fetches.fetch_add(1, Ordering::SeqCst);
+                    Json(idp.jwks.lock().unwrap().clone())
+                }),
+            )
+            .with_state(idp.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        (issuer, idp)
+    }
+
+    fn claims(issuer: &str) -> serde_json::Value {
+        serde_json::json!({
+            "iss": issuer,
+            "aud": CLIENT_ID,
+            "sub": "user-1",
+            "exp": chrono::Utc::now().timestamp() + 300,
+            "nonce": "n-1",
+            "preferred_username": "alice",
+        })
+    }
+
+    fn expect_invalid(result: Result<IdTokenClaims, OAuthError>, reason: &str) {
+        match result {
+            Err(OAuthError::InvalidIdToken(message)) => {
+                assert!(message.contains(reason), "{:?} lacks {:?}", message, reason)
+            }
+            Err(other) => panic!("expected InvalidIdToken, got {}", other),
+            Ok(_) => panic!("token was accepted"),
+        }
+    }
+
+    // Let the next unknown kid trigger a JWKS refresh without waiting a minute
+    async fn age_jwks_cache(provider: &OidcProvider) {
+        let mut cached = provider.jwks.write().await;
+        cached.fetched_at = Instant::now()
+            .checked_sub(JWKS_MIN_REFRESH_INTERVAL)
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn discovers_endpoints_and_validates_id_token() {
+        let key = TestKey::new("k1");
+        let (issuer, idp) = start_idp(&[&key]).await;
+
+        let provider = OidcProvider::discover(&issuer, CLIENT_ID, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            provider.metadata.token_endpoint,
+            format!("{}/token", issuer)
+        );
+        assert_eq!(idp.fetches(), 1);
+
+        let token = key.sign(&claims(&issuer));
+        let claims = provider
+            .validate_id_token(&token, Some("n-1"))
+            .await
+            .unwrap();
+        assert_eq!(claims.sub, "user-1");
+        assert_eq!(provider.user_info_from_claims(claims).username, "alice");
+        assert_eq!(idp.fetches(), 1);
+    }
+
+    #[tokio::test]
+    async fn rejects_discovery_document_for_another_issuer() {
+        let key = TestKey::new("k1");
+        let (issuer, idp) = start_idp(&[&key]).await;
+        *idp.issuer.lock().unwrap() = format!("{}/evil", issuer);
+
+        match OidcProvider::discover(&issuer, CLIENT_ID, None).await {
+            Err(OAuthError::DiscoveryFailed(message)) => assert!(message.contains("mismatch")),
+            Err(other) => panic!("expected DiscoveryFailed, got {}", other),
+            Ok(_) => panic!("discovery succeeded"),
+        }
+    }
+
+    #[tokio::test]
+    async fn refreshes_jwks_when_keys_rotate() {
+        let old_key = TestKey::new("k1");
+        let new_key = TestKey::new("k2");
+        let (issuer, idp) = start_idp(&[&old_key]).await;
+        let provider = OidcProvider::discover(&issuer, CLIENT_ID, None)
+            .await
+            .unwrap();
+
+        idp.publish(&[&new_key]);
+        let token = new_key.sign(&claims(&issuer));
+
+        // Unknown kids do not refetch more than once a minute
+        expect_invalid(
+            provider.validate_id_token(&token, Some("n-1")).await,
+            "No matching signing key",
+        );
+        assert_eq!(idp.fetches(), 1);
+
+        age_jwks_cache(&provider).await;
+        provider
+            .validate_id_token(&token, Some("n-1"))
+            .await
+            .unwrap();
+        assert_eq!(idp.fetches(), 2);
+
+        // The retired key is gone from the refreshed set
+        let stale = old_key.sign(&claims(&issuer));
+        expect_invalid(
+            provider.validate_id_token(&stale, Some("n-1")).await,
+            "No matching signing key",
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_issuer_audience_and_nonce() {
+        let key = TestKey::new("k1");
+        let (issuer, _idp) = start_idp(&[&key]).await;
+        let provider = OidcProvider::discover(&issuer, CLIENT_ID, None)
+            .await
+            .unwrap();
+
+        let mut wrong_issuer = claims(&issuer);
+        wrong_issuer["iss"] = "https://attacker.example".into();
+        expect_invalid(
+            provider
+                .validate_id_token(&key.sign(&wrong_issuer), Some("n-1"))
+                .await,
+            "InvalidIssuer",
+        );
+
+        let mut wrong_audience = claims(&issuer);
+        wrong_audience["aud"] = "another-client".into();
+        expect_invalid(
+            provider
+                .validate_id_token(&key.sign(&wrong_audience), Some("n-1"))
+                .await,
+            "InvalidAudience",
+        );
+
+        let token = key.sign(&claims(&issuer));
+        expect_invalid(
+            provider.validate_id_token(&token, Some("n-2")).await,
+            "Nonce mismatch",
+        );
+        expect_invalid(
+            provider.validate_id_token(&token, None).await,
+            "Nonce mismatch",
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_hs256_token() {
+        let key = TestKey::new("k1");
+        let (issuer, idp) = start_idp(&[&key]).await;
+        let provider = OidcProvider::discover(&issuer, CLIENT_ID, None)
+            .await
+            .unwrap();
+
+        // Signed with a shared secret but claiming the provider's kid
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some("k1".to_string());
+        let token = encode(
+            &header,
+            &claims(&issuer),
+            &EncodingKey::from_secret(b"client-secret"),
+        )
+        .unwrap();
+
+        expect_invalid(
+            provider.validate_id_token(&token, Some("n-1")).await,
+            "Unsupported signing algorithm",
+        );
+        assert_eq!(idp.fetches(), 1);
+    }
+}
@@ -0,0 +1,111 @@
+use std::io::Cursor;
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use image::{ImageFormat, Luma};
+use qrcode::{EcLevel, QrCode, render::svg};
+use serde::Deserialize;
+use thiserror::Error;
+
+const DEFAULT_SIZE: u32 = 256;
+const MIN_SIZE: u32 = 64;
+const MAX_SIZE: u32 = 2048;
+
+#[derive(Debug, Error)]
+pub enum QrError {
+    #[error("QR size must be between {MIN_SIZE} and {MAX_SIZE} pixels, got {0}")]
+    InvalidSize(u32),
+    #[error("Failed to encode QR code: {0}")]
+    Encode(#[from] qrcode::types::QrError),
+    #[error("Failed to write PNG: {0}")]
+    Png(#[from] image::ImageError),
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QrFormat {
+    #[default]
+    Svg,
+    Png,
+}
+
+/// Higher levels survive more damage (smudged or torn labels) at the cost of density
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum QrErrorCorrection {
+    L,
+    #[default]
+    M,
+    Q,
+    H,
+}
+
+impl From<QrErrorCorrection> for EcLevel {
+    fn from(level: QrErrorCorrection) -> Self {
+        match level {
+            QrErrorCorrection::L => EcLevel::L,
+            QrErrorCorrection::M => EcLevel::M,
+            QrErrorCorrection::Q => EcLevel::Q,
+            QrErrorCorrection::H => EcLevel::H,
+        }
+    }
+}
+
+/// Accepted both as query parameters and as a JSON object
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct QrOptions {
+    #[serde(default)]
+    pub format: QrFormat,
+    /// Minimum width and height in pixels, including the quiet zone
+    pub size: Option<u32>,
+    #[serde(default)]
+    pub error_correction: QrErrorCorrection,
+}
+
+pub struct QrImage {
+    pub content_type: &'static str,
+    pub extension: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+impl QrImage {
+    pub fn data_uri(&self) -> String {
+        format!(
+            "data:{};base64,{}",
+            self.content_type,
+            STANDARD.encode(&self.bytes)
+        )
+    }
+}
+
+pub fn render(data: &str, options: &QrOptions) -> Result<QrImage, QrError> {
+    let size = options.size.unwrap_or(DEFAULT_SIZE);
+    if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+        return Err(QrError::InvalidSize(size));
+    }
+
+    let code = QrCode::with_error_correction_level(data, options.error_correction.into())?;
+
+    match options.format {
+        QrFormat::Svg => {
+            let svg = code
+                .render::<svg::Color>()
+                .min_dimensions(size, size)
+                .build();
+            Ok(QrImage {
+                content_type: "image/svg+xml",
+                extension: "svg",
+                bytes: svg.into_bytes(),
+            })
+        }
+        QrFormat::Png => {
+            let image = code.render::<Luma<u8>>().min_dimensions(size, size).build();
+            let mut bytes = Vec::new();
+            image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)?;
+            Ok(QrImage {
+                content_type: "image/png",
+                extension: "png",
+                bytes,
+            })
+        }
+    }
+}
@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+#[cfg(feature = "redis")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::client_ip::resolve_client_ip;
+use crate::state::AppState;
+
+// How often the in-memory store drops buckets that have refilled completely
+const MEMORY_STORE_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Token bucket parameters: `capacity` requests per `period`, refilled continuously.
+///
+/// Parsed from strings like `60/m`, `10/s` or `1000/h`; `off` disables the limit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub capacity: u32,
+    pub period: Duration,
+}
+
+impl RateLimit {
+    pub const fn per_minute(capacity: u32) -> Self {
+        Self {
+            capacity,
+            period: Duration::from_secs(60),
+        }
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        self.capacity as f64 / self.period.as_secs_f64()
+    }
+}
+
+/// Wrapper so `Option<RateLimit>` can be read with `parse_with_default`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitSetting(pub Option<RateLimit>);
+
+impl FromStr for RateLimitSetting {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("off") || value == "0" {
+            return Ok(Self(None));
+        }
+
+        let (count, unit) = value
+            .split_once('/')
+            .ok_or_else(|| format!("expected <count>/<s|m|h>, got {:?}", value))?;
+        let capacity: u32 = count
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid request count {:?}", count))?;
+        let period = match unit.trim() {
+            "s" => Duration::from_secs(1),
+            "m" => Duration::from_secs(60),
+            "h" => Duration::from_secs(3600),
+            other => return Err(format!("unknown period {:?}", other)),
+        };
+
+        if capacity == 0 {
+            return Ok(Self(None));
+        }
+        Ok(Self(Some(RateLimit { capacity, period })))
+    }
+}
+
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Take one token from the bucket for `key`; on refusal returns how long to wait
+    async fn acquire(&self, key: &str, limit: RateLimit) -> Result<(), Duration>;
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+    /// When the bucket is back to capacity, by its own limit
+    full_at: Instant,
+}
+
+type Buckets = Mutex<HashMap<String, TokenBucket>>;
+
+/// Per-process buckets; each instance enforces its own limits
+pub struct MemoryStore {
+    buckets: Arc<Buckets>,
+}
+
+impl MemoryStore {
+    /// Starts a background sweep that ends when the store is dropped
+    pub fn new() -> Self {
+        let buckets = Arc::new(Buckets::default());
+        tokio::spawn(sweep_periodically(Arc::downgrade(&buckets)));
+        Self { buckets }
+    }
+}
+
+async fn sweep_periodically(buckets: Weak<Buckets>) {
+    let mut interval = tokio::time::interval(MEMORY_STORE_SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let Some(buckets) = buckets.upgrade() else {
+            return;
+        };
+        sweep(&buckets, Instant::now());
+    }
+}
+
+// A bucket that has refilled completely is the same as no bucket
+fn sweep(buckets: &Buckets, now: Instant) {
+    let mut buckets = buckets.lock().unwrap_or_else(|e| e.into_inner());
+    buckets.retain(|_, bucket| bucket.full_at > now);
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn acquire(&self, key: &str, limit: RateLimit) -> Result<(), Duration> {
+        let now = Instant::now();
+        let refill = limit.refill_per_sec();
+        let capacity = limit.capacity as f64;
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        let bucket = buckets.entry(key.to_string()).or_insert(TokenBucket {
+            tokens: capacity,
+            updated_at: now,
+            full_at: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill).min(capacity);
+        bucket.updated_at = now;
+
+        let result = if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / refill))
+        };
+        bucket.full_at = now + Duration::from_secs_f64((capacity - bucket.tokens) / refill);
+        result
+    }
+}
+
+/// Buckets shared by every instance through Redis
+#[cfg(feature = "redis")]
+pub struct RedisStore {
+    connection: redis::aio::ConnectionManager,
+    script: redis::Script,
+    // Outages are logged when they start and end, not on every request
+    available: AtomicBool,
+}
+
+#[cfg(feature = "redis")]
+impl RedisStore {
+    pub async fn connect(url: &str) -> Result<Self, redis::RedisError> {
+        let client = redis::Client::open(url)?;
+        let connection = redis::aio::ConnectionManager::new(client).await?;
+
+        // Refill and take atomically, using the Redis clock so instances agree.
+        // Returns 0 when allowed, otherwise the milliseconds until a token is available.
+        let script = redis::Script::new(
+            r#"
+            local capacity = tonumber(ARGV[1])
+            local rate = tonumber(ARGV[2])
+            local time = redis.call('TIME')
+            local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+            local state = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+            local tokens = tonumber(state[1]) or capacity
+            local ts = tonumber(state[2]) or now
+            tokens = math.min(capacity, tokens + (now - ts) * rate)
+            local wait = 0
+            if tokens >= 1 then
+                tokens = tokens - 1
+            else
+                wait = math.ceil((1 - tokens) / rate)
+            end
+            redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', now)
+            redis.call('PEXPIRE', KEYS[1], math.ceil(capacity / rate))
+            return wait
+            "#,
+        );
+
+        Ok(Self {
+            connection,
+            script,
+            available: AtomicBool::new(true),
+        })
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl RateLimitStore for RedisStore {
+    async fn acquire(&self, key: &str, limit: RateLimit) -> Result<(), Duration> {
+        let mut connection = self.connection.clone();
+        let result: Result<u64, _> = self
+            .script
+            .key(format!("gurl:ratelimit:{}", key))
+            .arg(limit.capacity)
+            .arg(limit.refill_per_sec() / 1000.0)
+            .invoke_async(&mut connection)
+            .await;
+
+        if result.is_ok()
+            && !self.available.load(Ordering::Relaxed)
+            && !self.available.swap(true, Ordering::Relaxed)
+        {
+            eprintln!("Rate limiter available again");
+        }
+
+        match result {
+            Ok(0) => Ok(()),
+            Ok(wait_ms) => Err(Duration::from_millis(wait_ms)),
+            Err(err) => {
+                // Fail open: an unavailable limiter must not take downloads down with it
+                if self.available.swap(false, Ordering::Relaxed) {
+                    eprintln!("Rate limiter unavailable, allowing all requests: {err}");
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+pub struct RateLimiter {
+    store: Box<dyn RateLimitStore>,
+    download_per_ip: Option<RateLimit>,
+    download_per_link: Option<RateLimit>,
+    auth_per_ip: Option<RateLimit>,
+}
+
+impl RateLimiter {
+    pub fn new(
+        store: Box<dyn RateLimitStore>,
+        download_per_ip: Option<RateLimit>,
+        download_per_link: Option<RateLimit>,
+        auth_per_ip: Option<RateLimit>,
+    ) -> Self {
+        Self {
+            store,
+            download_per_ip,
+            download_per_link,
+            auth_per_ip,
+        }
+    }
+
+    async fn acquire(&self, key: &str, limit: Option<RateLimit>) -> Result<(), Duration> {
+        match limit {
+            Some(limit) => self.store.acquire(key, limit).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Throttle the public download route by client IP and by link ID
+pub async fn limit_downloads(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limiter = &state.rate_limiter;
+    let ip = request_client_ip(&state, &request);
+    let link_id = known_link_id(&state, request.uri().path()).await;
+
+    if let Some(ip) = ip
+        && let Err(retry_after) = limiter
+            .acquire(&format!("download:ip:{}", ip), limiter.download_per_ip)
+            .await
+    {
+        return too_many_requests(retry_after);
+    }
+
+    if let Some(link_id) = link_id
+        && let Err(retry_after) = limiter
+            .acquire(
+                &format!("download:link:{}", link_id),
+                limiter.download_per_link,
+            )
+            .await
+    {
+        return too_many_requests(retry_after);
+    }
+
+    next.run(request).await
+}
+
+/// The ID of the link the last path segment names, slug or not. Unknown IDs get no
+/// per-link bucket, so made-up IDs cannot fill the store; the per-IP limit still applies.
+async fn known_link_id(state: &AppState, path: &str) -> Option<String> {
+    let segment = path.rsplit('/').next().unwrap_or_default();
+    let id = match state.slugs.read().await.get(segment) {
+        Some(id) => id.clone(),
+        None => segment.to_string(),
+    };
+    state.tickets.read().await.contains_key(&id).then_some(id)
+}
+
+/// Throttle the public upload routes by client IP, sharing the download budget
+pub async fn limit_uploads(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limiter = &state.rate_limiter;
+
+    if let Some(ip) = request_client_ip(&state, &request)
+        && let Err(retry_after) = limiter
+            .acquire(&format!("upload:ip:{}", ip), limiter.download_per_ip)
+            .await
+    {
+        return too_many_requests(retry_after);
+    }
+
+    next.run(request).await
+}
+
+/// Throttle the OAuth endpoints by client IP so the provider cannot be spammed through us
+pub async fn limit_auth(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let limiter = &state.rate_limiter;
+
+    if let Some(ip) = request_client_ip(&state, &request)
+        && let Err(retry_after) = limiter
+            .acquire(&format!("auth:ip:{}", ip), limiter.auth_per_ip)
+            .await
+    {
+        return too_many_requests(retry_after);
+    }
+
+    next.run(request).await
+}
+
+fn request_client_ip(state: &AppState, request: &Request) -> Option<IpAddr> {
+    let ConnectInfo(peer) = request.extensions().get::<ConnectInfo<SocketAddr>>()?;
+    Some(resolve_client_ip(
+        peer.ip(),
+        request.headers(),
+        &state.config.trusted_proxies,
+    ))
+}
+
+fn too_many_requests(retry_after: Duration) -> Response {
+    // Retry-After is whole seconds; round up so clients never retry too early
+    let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    let mut response = (StatusCode::TOO_MANY_REQUESTS, "Too many requests").into_response();
+    response.headers_mut().insert(
+        header::RETRY_AFTER,
+        HeaderValue::from_str(&seconds.max(1).to_string()).unwrap_or(HeaderValue::from_static("1")),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn sweep_drops_only_refilled_buckets() {
+        let store = MemoryStore::new();
+        let fast = RateLimit {
+            capacity: 10,
+            period: Duration::from_millis(100),
+        };
+        store.acquire("fast", fast).await.unwrap();
+        store
+            .acquire("slow", RateLimit::per_minute(10))
+            .await
+            .unwrap();
+
+        // Each bucket is judged by its own period, not the one in the latest request
+        sweep(&store.buckets, Instant::now() + Duration::from_secs(1));
+        let buckets = store.buckets.lock().unwrap();
+        assert!(!buckets.contains_key("fast"));
+        assert!(buckets.contains_key("slow"));
+    }
+
+    #[tokio::test]
+    async fn refuses_when_empty_and_reports_wait() {
+        let store = MemoryStore::new();
+        let limit = RateLimit::per_minute(1);
+        store.acquire("key", limit).await.unwrap();
+        let wait = store.acquire("key", limit).await.unwrap_err();
+        assert!(wait > Duration::from_secs(59) && wait <= Duration::from_secs(60));
+    }
+}
@@ -1,40 +1,131 @@
 use axum::{
     Json, Router,
-    extract::{Path, Query, State},
-    http::{HeaderValue, StatusCode, header},
-    response::{IntoResponse, Redirect},
+    body::Body,
+    extract::{DefaultBodyLimit, FromRequest, Multipart, Path, Query, Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    middleware,
+    response::{Html, IntoResponse, Redirect, Response},
     routing::{get, post},
 };
-use chrono::{Duration, Utc};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use bytes::{Bytes, BytesMut};
+use chrono::{DateTime, Duration, Utc};
+use futures_util::{Stream, StreamExt};
+use ipnet::IpNet;
 use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
 use uuid::Uuid;
 
 use crate::auth::{AuthUser, generate_token};
-use crate::oauth::{OAuthError, check_admin_permission, exchange_code_for_token, fetch_user_info};
-use crate::oss_client::OssClient;
-use crate::oss_client::{SigningError, build_signed_url};
-use crate::state::{AppState, DownloadTicket};
+use crate::cdn::CdnDomain;
+use crate::client_ip::ClientIp;
+use crate::config::AppConfig;
+use crate::content_disposition::{DispositionType, content_disposition};
+use crate::database::{
+    DownloadLink, NewDownloadLink, NewPendingUpload, NewUploadLink, PendingUpload, UploadLink,
+    UploadSession, UploadedPart,
+};
+use crate::jwt_keys::JwkSet;
+use crate::landing_page::LandingPage;
+use crate::link_preview::{PreviewAction, is_preview_client};
+use crate::metrics::{self, Snapshot};
+use crate::oauth::{
+    OAuthError, build_authorize_url, check_admin_permission, clear_state_cookie,
+    exchange_code_for_token, fetch_user_info, new_login_request, state_cookie,
+    state_cookie_matches,
+};
+use crate::object_search::{SearchEvent, SearchFilter, SearchQuery};
+use crate::oss_client::{
+    CacheStats, CompletedPart, ListObjectsParams, ObjectLocation, ObjectMetadata, OssClient,
+    OssError, PresignedRequest, SignParams, SigningError, UploadTarget, V4_MAX_EXPIRES_SECS,
+    build_presigned_part, build_presigned_put, build_signed_url,
+};
+use crate::qr::{self, QrError, QrImage, QrOptions};
+use crate::rate_limit;
+use crate::state::{AppState, DownloadTicket, UploadClaim};
+use crate::watermark::{self, Watermark, WatermarkMode};
 
 pub fn create_router(state: AppState) -> Router {
     let download_prefix = format!("/{}", state.config.download_prefix);
+    let upload_prefix = format!("/{}", state.config.upload_prefix);
 
     Router::new()
         .route("/healthz", get(health_check))
+        .route("/metrics", get(metrics))
+        .route("/.well-known/jwks.json", get(jwks))
         // OAuth2 authentication routes
-        .route("/api/oauth/callback", get(oauth_callback))
+        .merge(
+            Router::new()
+                .route("/api/oauth/login", get(oauth_login))
+                .route("/api/oauth/callback", get(oauth_callback))
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    rate_limit::limit_auth,
+                )),
+        )
         // Frontend domain routes - gurl.honahec.cc (management functions)
         .route("/sign", post(create_signed_link))
         .route("/buckets", get(list_buckets))
-        .route("/objects", get(list_objects))
+        .route("/oss/stats", get(oss_stats))
+        .route("/objects", get(list_objects).delete(delete_object))
+        .route("/objects/copy", post(copy_object))
+        .route("/objects/rename", post(rename_object))
+        .route("/objects/metadata", get(get_object_metadata))
+        .route("/objects/search", get(search_objects))
+        .route(
+            "/objects/upload",
+            post(upload_object).layer(DefaultBodyLimit::disable()),
+        )
+        .route(
+            "/objects/upload/:upload_id",
+            get(get_pending_upload).delete(abort_pending_upload),
+        )
         .route("/links", get(list_links))
         .route("/links/:id", get(get_link_info))
         .route("/links/:id", axum::routing::delete(delete_link))
+        .route("/links/:id/qr", get(get_link_qr))
         .route("/cleanup", post(cleanup_expired_links))
+        .route(
+            "/upload-links",
+            get(list_upload_links).post(create_upload_link),
+        )
+        .route(
+            "/upload-links/:id",
+            axum::routing::delete(delete_upload_link),
+        )
         // Backend domain routes - api.honahec.cc (public access)
         .nest(
             &download_prefix,
-            Router::new().route("/:id", get(resolve_download)),
+            Router::new()
+                .route(
+                    "/:id",
+                    get(resolve_download)
+                        .head(probe_download)
+                        .post(confirm_download),
+                )
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    rate_limit::limit_downloads,
+                )),
+        )
+        .nest(
+            &upload_prefix,
+            Router::new()
+                .route("/:id", get(upload_link_info).post(start_upload))
+                .route("/:id/complete", post(complete_upload))
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    rate_limit::limit_uploads,
+                )),
         )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            metrics::track_requests,
+        ))
         .with_state(state)
 }
 
@@ -42,12 +133,89 @@ async fn health_check() -> &'static str {
     "ok"
 }
 
+async fn metrics(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Some(token) = &state.config.metrics_token
+        && !metrics::authorized(&headers, token)
+    {
+        return ApiError::Unauthorized.into_response();
+    }
+
+    let snapshot = Snapshot {
+        tickets: state.tickets.read().await.len(),
+        oss_operations: state
+            .oss
+            .as_ref()
+            .map(|client| client.operation_stats())
+            .unwrap_or_default(),
+        bucket_cache: state.oss.as_ref().map(|client| client.bucket_cache_stats()),
+    };
+
+    (
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        state.metrics.render(&snapshot),
+    )
+        .into_response()
+}
+
+// Public keys for anyone verifying our session tokens; empty when signing with HS256
+async fn jwks(State(state): State<AppState>) -> Json<JwkSet> {
+    Json(state.jwt_keys.jwks().clone())
+}
+
+#[derive(Debug, Serialize)]
+pub struct OAuthLoginResponse {
+    pub authorize_url: String,
+    pub state: String,
+    pub expires_in: i64,
+}
+
+// Start an OAuth2 login: state, nonce and PKCE verifier stay on the server
+async fn oauth_login(State(state): State<AppState>) -> Result<Response, ApiError> {
+    // The state cookie needs credentialed CORS, which is never granted to every origin
+    if state.config.allows_any_origin() {
+        return Err(ApiError::Internal(
+            "OAuth login requires CORS_ALLOWED_ORIGINS to list the frontend origins".to_string(),
+        ));
+    }
+
+    let authorize_endpoint = state
+        .oidc
+        .as_ref()
+        .map(|provider| provider.metadata.authorization_endpoint.as_str())
+        .unwrap_or(&state.config.oauth_authorize_url);
+
+    let login = new_login_request();
+    let authorize_url =
+        build_authorize_url(&state.config, authorize_endpoint, &login).map_err(ApiError::OAuth)?;
+    let expires_in = (login.session.expires_at - Utc::now()).num_seconds();
+
+    {
+        let now = Utc::now();
+        let mut sessions = state.oauth_sessions.write().await;
+        // Drop abandoned logins so the map cannot grow without bound
+        sessions.retain(|_, session| session.expires_at > now);
+        sessions.insert(login.state.clone(), login.session);
+    }
+
+    let cookie = state_cookie(&login.state, state.config.secure_cookies());
+    Ok((
+        [(header::SET_COOKIE, cookie)],
+        Json(OAuthLoginResponse {
+            authorize_url,
+            state: login.state,
+            expires_in,
+        }),
+    )
+        .into_response())
+}
+
 #[derive(Debug, Deserialize)]
 pub struct OAuthCallbackQuery {
     pub code: String,
-    #[allow(dead_code)]
     pub state: String,
-    pub code_verifier: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -60,23 +228,87 @@ pub struct OAuthCallbackResponse {
 // OAuth2 callback handler
 async fn oauth_callback(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Query(query): Query<OAuthCallbackQuery>,
+) -> Response {
+    let result = complete_oauth_login(&state, &headers, query).await;
+    let outcome = match &result {
+        Ok(_) => "success",
+        Err(ApiError::OAuth(OAuthError::InvalidState)) => "invalid_state",
+        Err(ApiError::OAuth(OAuthError::InvalidSession)) => "expired_session",
+        Err(ApiError::OAuth(OAuthError::PermissionDenied)) => "permission_denied",
+        Err(ApiError::OAuth(OAuthError::InvalidIdToken(_))) => "invalid_id_token",
+        Err(ApiError::OAuth(_)) => "provider_error",
+        Err(_) => "error",
+    };
+    state.metrics.oauth_callback(outcome);
+    // The state cookie is single-use, like the state itself
+    let cookie = clear_state_cookie(state.config.secure_cookies());
+    ([(header::SET_COOKIE, cookie)], result).into_response()
+}
+
+async fn complete_oauth_login(
+    state: &AppState,
+    headers: &HeaderMap,
+    query: OAuthCallbackQuery,
 ) -> Result<Json<OAuthCallbackResponse>, ApiError> {
-    // Exchange authorization code for access token
-    let token_response = exchange_code_for_token(&state.config, &query.code, &query.code_verifier)
-        .await
-        .map_err(ApiError::OAuth)?;
+    if query.state.is_empty() || !state_cookie_matches(headers, &query.state) {
+        return Err(ApiError::OAuth(OAuthError::InvalidState));
+    }
 
-    // Fetch user information
-    let user_info = fetch_user_info(&state.config, &token_response.access_token)
+    // Each state is single-use: remove it whether or not the rest of the login succeeds
+    let session = state
+        .oauth_sessions
+        .write()
         .await
-        .map_err(ApiError::OAuth)?;
+        .remove(&query.state)
+        .ok_or(ApiError::OAuth(OAuthError::InvalidState))?;
+
+    if Utc::now() > session.expires_at {
+        return Err(ApiError::OAuth(OAuthError::InvalidSession));
+    }
+
+    let token_url = state
+        .oidc
+        .as_ref()
+        .map(|provider| provider.metadata.token_endpoint.as_str())
+        .unwrap_or(&state.config.oauth_token_url);
+
+    // Exchange authorization code for access token
+    let token_response = exchange_code_for_token(
+        &state.config,
+        token_url,
+        &query.code,
+        &session.code_verifier,
+    )
+    .await
+    .map_err(ApiError::OAuth)?;
+
+    let user_info = match &state.oidc {
+        // With OIDC the signed id_token is the source of truth for the user's identity
+        Some(provider) => {
+            let id_token = token_response.id_token.as_deref().ok_or_else(|| {
+                ApiError::OAuth(OAuthError::InvalidIdToken(
+                    "Token response did not include an id_token".to_string(),
+                ))
+            })?;
+            let claims = provider
+                .validate_id_token(id_token, Some(&session.nonce))
+                .await
+                .map_err(ApiError::OAuth)?;
+            provider.user_info_from_claims(claims)
+        }
+        // Fetch user information
+        None => fetch_user_info(&state.config, &token_response.access_token)
+            .await
+            .map_err(ApiError::OAuth)?,
+    };
 
     // Check admin permission
     check_admin_permission(&user_info).map_err(ApiError::OAuth)?;
 
     // Generate JWT token
-    let jwt_token = generate_token(&user_info.username, &state.config)
+    let jwt_token = generate_token(&user_info.username, &state.config, &state.jwt_keys)
         .map_err(|_| ApiError::Internal("Failed to generate token".to_string()))?;
 
     Ok(Json(OAuthCallbackResponse {
@@ -86,22 +318,52 @@ async fn oauth_callback(
     }))
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct CreateLinkRequest {
     pub object_key: String,
     pub bucket: Option<String>,
     pub expires_in_seconds: i64,
     pub max_downloads: Option<u32>,
     pub download_filename: Option<String>,
+    /// `inline` lets browsers display the file; defaults to `attachment` with a filename
+    pub disposition: Option<DispositionType>,
+    /// Who the link is for, e.g. an email address; recorded with the link
+    pub recipient: Option<String>,
+    /// Serve the file through the backend with `recipient` marked in (PDF only)
+    pub watermark: Option<WatermarkMode>,
     pub endpoint: Option<String>,
+    pub allowed_cidrs: Option<Vec<String>>,
+    pub bind_client_ip: Option<bool>,
+    /// Always (true) or never (false) hand out the intranet host; unset decides by
+    /// whether the downloader is in `OSS_INTERNAL_NETWORKS`
+    pub use_intranet: Option<bool>,
+    /// `x-oss-process` applied by OSS on download, e.g. `image/resize,w_200`
+    pub process: Option<String>,
+    /// Served instead of the object's stored Content-Type
+    pub response_content_type: Option<String>,
+    pub response_cache_control: Option<String>,
+    pub response_content_language: Option<String>,
+    pub slug: Option<String>,
+    /// Show a page with file details instead of redirecting straight away
+    pub landing_page: Option<bool>,
+    /// Start restoring the object if it is in an archive storage class
+    pub restore: Option<bool>,
+    /// Also return a QR code of the link as a data URI
+    pub qr: Option<QrOptions>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct CreateLinkResponse {
-    pub id: Uuid,
+    pub id: String,
+    pub slug: Option<String>,
     pub url: String,
     pub expires_at: String,
     pub max_downloads: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qr_code: Option<String>,
+    /// Why downloads go to OSS although the bucket has a CDN domain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cdn_bypassed: Option<&'static str>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -126,11 +388,61 @@ pub struct DownloadLinkResponse {
     pub downloads_served: i64,
     pub created_at: String,
     pub download_filename: Option<String>,
+    pub disposition: Option<DispositionType>,
+    pub recipient: Option<String>,
+    pub watermark: Option<WatermarkMode>,
     pub endpoint: Option<String>,
+    pub allowed_cidrs: Vec<String>,
+    pub bind_client_ip: Option<bool>,
+    pub use_intranet: Option<bool>,
+    pub process: Option<String>,
+    pub response_content_type: Option<String>,
+    pub response_cache_control: Option<String>,
+    pub response_content_language: Option<String>,
+    pub slug: Option<String>,
+    pub landing_page: bool,
+    pub preview_hits: i64,
+    pub object_size: Option<i64>,
+    pub object_etag: Option<String>,
+    pub restore_ready_at: Option<String>,
     pub is_expired: bool,
     pub download_url: String,
 }
 
+impl DownloadLinkResponse {
+    fn from_link(link: DownloadLink, config: &AppConfig) -> Self {
+        Self {
+            download_url: public_link_url(config, &link.id, link.slug.as_deref()),
+            id: link.id,
+            object_key: link.object_key,
+            bucket: link.bucket,
+            expires_at: link.expires_at.to_rfc3339(),
+            max_downloads: link.max_downloads,
+            downloads_served: link.downloads_served,
+            created_at: link.created_at.to_rfc3339(),
+            download_filename: link.download_filename,
+            disposition: link.disposition,
+            recipient: link.recipient,
+            watermark: link.watermark,
+            endpoint: link.endpoint,
+            allowed_cidrs: link.allowed_cidrs,
+            bind_client_ip: link.bind_client_ip,
+            use_intranet: link.use_intranet,
+            process: link.process,
+            response_content_type: link.response_content_type,
+            response_cache_control: link.response_cache_control,
+            response_content_language: link.response_content_language,
+            slug: link.slug,
+            landing_page: link.landing_page,
+            preview_hits: link.preview_hits,
+            object_size: link.object_size,
+            object_etag: link.object_etag,
+            restore_ready_at: link.restore_ready_at.map(|at| at.to_rfc3339()),
+            is_expired: link.is_expired,
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct DeleteResponse {
     pub success: bool,
@@ -147,12 +459,36 @@ async fn create_signed_link(
     State(state): State<AppState>,
     Json(payload): Json<CreateLinkRequest>,
 ) -> Result<Json<CreateLinkResponse>, ApiError> {
+    create_link(&state, payload).await.map(Json)
+}
+
+async fn create_link(
+    state: &AppState,
+    payload: CreateLinkRequest,
+) -> Result<CreateLinkResponse, ApiError> {
     if payload.object_key.is_empty() {
         return Err(ApiError::BadRequest(
             "Object key cannot be empty".to_string(),
         ));
     }
 
+    let process = text_option("process", payload.process)?;
+    let response_content_type =
+        text_option("response_content_type", payload.response_content_type)?;
+    let response_cache_control =
+        text_option("response_cache_control", payload.response_cache_control)?;
+    let response_content_language = text_option(
+        "response_content_language",
+        payload.response_content_language,
+    )?;
+
+    let recipient = text_option("recipient", payload.recipient)?;
+    if payload.watermark.is_some() && recipient.is_none() {
+        return Err(ApiError::BadRequest(
+            "A recipient is required for watermarked links".to_string(),
+        ));
+    }
+
     let expires_in = if payload.expires_in_seconds > 0 {
         payload.expires_in_seconds
     } else {
@@ -161,9 +497,106 @@ async fn create_signed_link(
 
     let expires_at = Utc::now() + Duration::seconds(expires_in);
 
-    let id = Uuid::new_v4();
+    let allowed_cidrs = payload
+        .allowed_cidrs
+        .unwrap_or_default()
+        .iter()
+        .map(|cidr| parse_cidr(cidr))
+        .collect::<Result<Vec<IpNet>, ApiError>>()?;
+
+    let slug = payload
+        .slug
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty());
+    if let Some(slug) = slug {
+        validate_slug(slug)?;
+        if identifier_in_use(state, slug).await? {
+            return Err(ApiError::Conflict(format!(
+                "Slug '{}' is already in use",
+                slug
+            )));
+        }
+    }
+    let slug = slug.map(str::to_string);
+
+    let bucket = payload
+        .bucket
+        .clone()
+        .or_else(|| state.config.aliyun_default_bucket.clone());
+    let strict = state.config.oss_strict_object_check;
+    let metadata = match &bucket {
+        Some(bucket) => match head_object(
+            state,
+            bucket,
+            &payload.object_key,
+            payload.endpoint.as_deref(),
+        )
+        .await
+        {
+            Ok(Some(metadata)) => Some(metadata),
+            Ok(None) if strict => {
+                return Err(ApiError::BadRequest(format!(
+                    "Object '{}' does not exist in bucket '{}'",
+                    payload.object_key, bucket
+                )));
+            }
+            Err(e) if strict => {
+                return Err(ApiError::Internal(format!("Failed to check object: {}", e)));
+            }
+            // Outside strict mode the lookup is best effort
+            _ => None,
+        },
+        None => None,
+    };
+
+    if payload.watermark.is_some() {
+        let content_type = metadata
+            .as_ref()
+            .and_then(|metadata| metadata.content_type.as_deref());
+        if !watermark::is_supported(&payload.object_key, content_type) {
+            return Err(ApiError::BadRequest(
+                "Only PDF files can be watermarked".to_string(),
+            ));
+        }
+        if let Some(metadata) = &metadata
+            && metadata.size > state.config.watermark_max_bytes
+        {
+            return Err(ApiError::BadRequest(format!(
+                "Files over {} bytes cannot be watermarked",
+                state.config.watermark_max_bytes
+            )));
+        }
+    }
+
+    // Archived objects cannot be downloaded until a restored copy exists
+    let restore_ready_at = match (&metadata, &bucket) {
+        (Some(metadata), Some(bucket)) if metadata.needs_restore() => {
+            if !metadata.restore_in_progress() {
+                if !payload.restore.unwrap_or(state.config.oss_auto_restore) {
+                    return Err(ApiError::BadRequest(format!(
+                        "Object '{}' is in {} storage and must be restored before it can be downloaded; set \"restore\": true to start a restore",
+                        payload.object_key,
+                        metadata.storage_class.as_deref().unwrap_or_default()
+                    )));
+                }
+                start_restore(
+                    state,
+                    bucket,
+                    &payload.object_key,
+                    payload.endpoint.as_deref(),
+                )
+                .await
+                .map_err(|e| ApiError::Internal(format!("Failed to restore object: {}", e)))?;
+            }
+            Some(Utc::now() + metadata.estimated_restore_time(state.config.oss_restore_tier))
+        }
+        _ => None,
+    };
+
+    let id = generate_link_id(state).await?;
     let ticket = DownloadTicket {
-        id,
+        id: id.clone(),
         bucket_override: payload.bucket.clone(),
         object_key: payload.object_key.clone(),
         expires_at,
@@ -171,301 +604,2711 @@ async fn create_signed_link(
         downloads_served: 0,
         created_at: Utc::now(),
         download_filename: payload.download_filename.clone(),
+        disposition: payload.disposition,
+        recipient: recipient.clone(),
+        watermark: payload.watermark,
         endpoint_override: payload.endpoint.clone(),
+        allowed_cidrs: allowed_cidrs.clone(),
+        bind_client_ip: payload
+            .bind_client_ip
+            .unwrap_or(state.config.oss_bind_source_ip),
+        use_intranet: payload.use_intranet,
+        process: process.clone(),
+        response_content_type: response_content_type.clone(),
+        response_cache_control: response_cache_control.clone(),
+        response_content_language: response_content_language.clone(),
+        landing_page: payload.landing_page.unwrap_or(false),
+        restore_ready_at,
     };
 
     // Store to database
     state
         .database
-        .create_download_link(
-            id,
-            payload.object_key,
-            payload.bucket,
+        .create_download_link(NewDownloadLink {
+            id: id.clone(),
+            object_key: payload.object_key,
+            bucket: payload.bucket,
             expires_at,
-            payload.max_downloads,
-            payload.download_filename,
-            payload.endpoint,
-        )
+            max_downloads: payload.max_downloads,
+            download_filename: payload.download_filename,
+            disposition: payload.disposition,
+            recipient,
+            watermark: payload.watermark,
+            endpoint: payload.endpoint,
+            allowed_cidrs: allowed_cidrs.iter().map(IpNet::to_string).collect(),
+            bind_client_ip: payload.bind_client_ip,
+            use_intranet: payload.use_intranet,
+            process,
+            response_content_type,
+            response_cache_control,
+            response_content_language,
+            slug: slug.clone(),
+            landing_page: payload.landing_page.unwrap_or(false),
+            object_size: metadata.as_ref().map(|metadata| metadata.size),
+            object_etag: metadata.and_then(|metadata| metadata.etag),
+            restore_ready_at,
+        })
         .await
-        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?;
+        .map_err(|e| match (e.downcast_ref::<sqlx::Error>(), &slug) {
+            // Another request claimed the slug since the check above
+            (Some(sqlx::Error::Database(db)), Some(slug)) if db.is_unique_violation() => {
+                ApiError::Conflict(format!("Slug '{}' is already in use", slug))
+            }
+            _ => ApiError::Internal(format!("Database error: {}", e)),
+        })?;
+
+    let cdn_bypassed = cdn_domain(state, &ticket).and_then(|cdn| {
+        if ticket.watermark.is_some() {
+            Some("Watermarked downloads are served by the backend")
+        } else if ticket.overrides_response() {
+            Some("CDN URLs cannot carry a filename, disposition or other response overrides")
+        } else if ticket.bind_client_ip {
+            Some("CDN URLs cannot be bound to the client IP")
+        } else if cdn.outlives(ticket.expires_at, Utc::now()) {
+            Some("The link expires before the CDN's URL validity period would end")
+        } else {
+            None
+        }
+    });
 
     // Store ticket to memory
     {
         let mut tickets = state.tickets.write().await;
-        tickets.insert(id, ticket);
+        tickets.insert(id.clone(), ticket);
+    }
+    if let Some(slug) = &slug {
+        state.slugs.write().await.insert(slug.clone(), id.clone());
     }
 
-    let download_url = format!(
-        "{}/{}",
-        state.config.public_base_url.trim_end_matches('/'),
-        format!("{}/{}", state.config.download_prefix, id)
-    );
+    let download_url = public_link_url(&state.config, &id, slug.as_deref());
+
+    let qr_code = payload
+        .qr
+        .map(|options| render_qr(&download_url, &options))
+        .transpose()?
+        .map(|image| image.data_uri());
 
-    Ok(Json(CreateLinkResponse {
+    state.metrics.link_created();
+
+    Ok(CreateLinkResponse {
         id,
+        slug,
         url: download_url,
         expires_at: expires_at.to_rfc3339(),
         max_downloads: payload.max_downloads,
-    }))
+        qr_code,
+        cdn_bypassed,
+    })
+}
+
+fn cdn_domain<'a>(state: &'a AppState, ticket: &DownloadTicket) -> Option<&'a CdnDomain> {
+    ticket
+        .bucket_override
+        .as_ref()
+        .or(state.config.aliyun_default_bucket.as_ref())
+        .and_then(|bucket| state.config.cdn_domains.get(bucket))
 }
 
 async fn resolve_download(
-    Path(id): Path<Uuid>,
+    Path(id_or_slug): Path<String>,
+    ClientIp(client_ip): ClientIp,
+    headers: HeaderMap,
     State(state): State<AppState>,
-) -> Result<Redirect, (StatusCode, String)> {
-    let now = Utc::now();
+) -> Result<Response, DownloadError> {
+    let result = serve_download(&state, id_or_slug, client_ip, &headers).await;
+    record_denial(&state, &result);
+    result
+}
 
-    // Get ticket
-    let tickets = state.tickets.read().await;
-    let ticket = tickets
-        .get(&id)
-        .ok_or_else(|| (StatusCode::NOT_FOUND, "Download link not found".to_string()))?;
+async fn serve_download(
+    state: &AppState,
+    id_or_slug: String,
+    client_ip: IpAddr,
+    headers: &HeaderMap,
+) -> Result<Response, DownloadError> {
+    let id = resolve_ticket_id(state, id_or_slug).await;
+    let is_preview = is_preview_client(headers, &state.config.preview_user_agents);
 
-    // Check if expired
-    if now > ticket.expires_at {
-        return Err((StatusCode::GONE, "Download link has expired".to_string()));
-    }
+    let landing_ticket = {
+        let tickets = state.tickets.read().await;
+        let ticket = tickets.get(&id).ok_or_else(link_not_found)?;
+        check_ticket(ticket, client_ip)?;
+        (is_preview || ticket.landing_page).then(|| ticket.clone())
+    };
 
-    // Check download count limit
-    if let Some(max_downloads) = ticket.max_downloads {
-        if ticket.downloads_served >= max_downloads {
-            return Err((
-                StatusCode::TOO_MANY_REQUESTS,
-                "Download limit exceeded".to_string(),
+    if is_preview {
+        let _ = state.database.record_preview(&id).await;
+        if state.config.preview_action == PreviewAction::Deny {
+            return Err(DownloadError::Rejected(
+                StatusCode::FORBIDDEN,
+                "Link previews are not allowed".to_string(),
             ));
         }
     }
 
-    drop(tickets);
+    ensure_available(state, &id).await?;
 
-    // Update download count
-    let mut tickets_mut = state.tickets.write().await;
-    if let Some(ticket_mut) = tickets_mut.get_mut(&id) {
-        ticket_mut.downloads_served += 1;
+    match landing_ticket {
+        // Unfurlers get the page without the OSS lookup and never the signed URL
+        Some(ticket) => Ok(render_landing_page(state, &ticket, !is_preview)
+            .await
+            .into_response()),
+        None => match redeem_download(state, &id, client_ip).await? {
+            Redemption::Redirect(url) => Ok(Redirect::temporary(&url).into_response()),
+            Redemption::Watermarked(download) => serve_watermarked(state, download).await,
+        },
     }
-    drop(tickets_mut);
-
-    // Update download count in database
-    let _ = state.database.increment_downloads(&id.to_string()).await;
-
-    // Re-fetch ticket info for generating signed URL
-    let tickets = state.tickets.read().await;
-    let ticket = tickets.get(&id).unwrap();
+}
 
-    // Generate signed download URL
-    let signed_url = build_signed_url(
-        &state.config,
-        ticket.bucket_override.as_deref(),
-        &ticket.object_key,
-        ticket.expires_at,
-        ticket.download_filename.as_deref(),
-        ticket.endpoint_override.as_deref(),
-    )
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to generate download URL".to_string(),
-        )
-    })?;
+// HEAD reports whether the link is usable without counting a download
+async fn probe_download(
+    Path(id_or_slug): Path<String>,
+    ClientIp(client_ip): ClientIp,
+    State(state): State<AppState>,
+) -> Result<StatusCode, DownloadError> {
+    let id = resolve_ticket_id(&state, id_or_slug).await;
+    {
+        let tickets = state.tickets.read().await;
+        let ticket = tickets.get(&id).ok_or_else(link_not_found)?;
+        check_ticket(ticket, client_ip)?;
+    }
 
-    Ok(Redirect::temporary(&signed_url.url))
+    let _ = state.database.record_preview(&id).await;
+    ensure_available(&state, &id).await?;
+    Ok(StatusCode::OK)
 }
 
-// Get links list
-async fn list_links(
-    _user: AuthUser,
-    Query(params): Query<ListLinksQuery>,
+// Submitted by the landing page's Download button
+async fn confirm_download(
+    Path(id_or_slug): Path<String>,
+    ClientIp(client_ip): ClientIp,
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, ApiError> {
-    let links = state
-        .database
-        .list_download_links(params.limit, params.offset)
-        .await
-        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?;
+) -> Result<Response, DownloadError> {
+    let result = async {
+        let id = resolve_ticket_id(&state, id_or_slug).await;
+        ensure_available(&state, &id).await?;
+        match redeem_download(&state, &id, client_ip).await? {
+            // 303 so the browser follows up with a GET
+            Redemption::Redirect(url) => Ok(Redirect::to(&url).into_response()),
+            Redemption::Watermarked(download) => serve_watermarked(&state, download).await,
+        }
+    }
+    .await;
+    record_denial(&state, &result);
+    result
+}
 
-    let download_links: Vec<DownloadLinkResponse> = links
-        .into_iter()
-        .map(|link| DownloadLinkResponse {
-            id: link.id.clone(),
-            object_key: link.object_key,
-            bucket: link.bucket,
-            expires_at: link.expires_at.to_rfc3339(),
-            max_downloads: link.max_downloads,
-            downloads_served: link.downloads_served,
-            created_at: link.created_at.to_rfc3339(),
-            download_filename: link.download_filename,
-            endpoint: link.endpoint,
-            is_expired: link.is_expired,
-            download_url: format!(
-                "{}/{}",
-                state.config.public_base_url.trim_end_matches('/'),
-                format!("{}/{}", state.config.download_prefix, link.id)
-            ),
-        })
-        .collect();
+fn record_denial(state: &AppState, result: &Result<Response, DownloadError>) {
+    let reason = match result {
+        Ok(_) => return,
+        Err(DownloadError::Rejected(status, _)) => metrics::denial_reason(*status),
+        Err(DownloadError::Restoring(_)) => "restoring",
+    };
+    state.metrics.download_denied(reason);
+}
 
-    let response = Json(ListLinksResponse {
-        total: download_links.len(),
-        links: download_links,
-    });
+pub enum DownloadError {
+    Rejected(StatusCode, String),
+    /// The archived object is not readable yet; estimated completion
+    Restoring(DateTime<Utc>),
+}
 
-    let mut response = response.into_response();
-    response.headers_mut().insert(
-        header::CACHE_CONTROL,
-        HeaderValue::from_static("no-store, no-cache, must-revalidate"),
-    );
-    response
-        .headers_mut()
-        .insert(header::PRAGMA, HeaderValue::from_static("no-cache"));
-    response
-        .headers_mut()
-        .insert(header::EXPIRES, HeaderValue::from_static("0"));
+impl From<(StatusCode, String)> for DownloadError {
+    fn from((status, message): (StatusCode, String)) -> Self {
+        DownloadError::Rejected(status, message)
+    }
+}
 
-    Ok(response)
+impl IntoResponse for DownloadError {
+    fn into_response(self) -> Response {
+        match self {
+            DownloadError::Rejected(status, message) => (status, message).into_response(),
+            DownloadError::Restoring(ready_at) => {
+                let retry_after = (ready_at - Utc::now()).num_seconds().max(1);
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    [(header::RETRY_AFTER, retry_after.to_string())],
+                    format!(
+                        "The file is being restored from archive storage, try again at about {}",
+                        ready_at.format("%Y-%m-%d %H:%M UTC")
+                    ),
+                )
+                    .into_response()
+            }
+        }
+    }
 }
 
-// Get single link info
-async fn get_link_info(
-    _user: AuthUser,
-    Path(id): Path<String>,
-    State(state): State<AppState>,
-) -> Result<Json<DownloadLinkResponse>, ApiError> {
-    let link = state
-        .database
-        .get_download_link(&id)
-        .await
-        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?
-        .ok_or_else(|| ApiError::BadRequest("Link not found".to_string()))?;
+// How long to wait before asking OSS again once the estimate has passed
+const RESTORE_RECHECK_INTERVAL: Duration = Duration::minutes(10);
 
-    let response = DownloadLinkResponse {
-        id: link.id.clone(),
-        object_key: link.object_key,
-        bucket: link.bucket,
-        expires_at: link.expires_at.to_rfc3339(),
-        max_downloads: link.max_downloads,
-        downloads_served: link.downloads_served,
-        created_at: link.created_at.to_rfc3339(),
-        download_filename: link.download_filename,
-        endpoint: link.endpoint,
-        is_expired: link.is_expired,
-        download_url: format!(
-            "{}/{}",
-            state.config.public_base_url.trim_end_matches('/'),
-            format!("{}/{}", state.config.download_prefix, link.id)
-        ),
+/// Ask OSS whether the object can be served: 404 once it has been deleted, 503
+/// while a pending restore has not completed
+async fn ensure_available(state: &AppState, id: &str) -> Result<(), DownloadError> {
+    let (ready_at, bucket, object_key, endpoint) = {
+        let tickets = state.tickets.read().await;
+        let Some(ticket) = tickets.get(id) else {
+            return Ok(());
+        };
+        (
+            ticket.restore_ready_at,
+            ticket
+                .bucket_override
+                .clone()
+                .or_else(|| state.config.aliyun_default_bucket.clone()),
+            ticket.object_key.clone(),
+            ticket.endpoint_override.clone(),
+        )
     };
 
-    Ok(Json(response))
-}
-
-// Delete link
-async fn delete_link(
-    _user: AuthUser,
-    Path(id): Path<String>,
-    State(state): State<AppState>,
-) -> Result<Json<DeleteResponse>, ApiError> {
-    let deleted = state
-        .database
-        .delete_download_link(&id)
-        .await
-        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?;
+    if let Some(ready_at) = ready_at
+        && Utc::now() < ready_at
+    {
+        return Err(DownloadError::Restoring(ready_at));
+    }
 
-    if deleted {
-        // Also remove from memory
-        if let Ok(uuid) = Uuid::parse_str(&id) {
-            let mut tickets = state.tickets.write().await;
-            tickets.remove(&uuid);
+    let Some(bucket) = bucket else {
+        return Ok(());
+    };
+    let metadata = match head_object(state, &bucket, &object_key, endpoint.as_deref()).await {
+        Ok(Some(metadata)) => metadata,
+        Ok(None) => {
+            return Err(DownloadError::Rejected(
+                StatusCode::NOT_FOUND,
+                "The file for this download link has been deleted".to_string(),
+            ));
         }
+        // OSS unreachable: let it have the final word on the redirect
+        Err(_) => return Ok(()),
+    };
+    if ready_at.is_none() {
+        return Ok(());
+    }
 
-        Ok(Json(DeleteResponse {
-            success: true,
-            message: "Link deleted successfully".to_string(),
-        }))
+    // The restore estimate has passed; check whether the restored copy is readable yet
+    let next_ready_at = if metadata.needs_restore() {
+        // A finished restore can lapse again; start another one if so
+        if !metadata.restore_in_progress() {
+            let _ = start_restore(state, &bucket, &object_key, endpoint.as_deref()).await;
+        }
+        Some(Utc::now() + RESTORE_RECHECK_INTERVAL)
     } else {
-        Ok(Json(DeleteResponse {
-            success: false,
-            message: "Link not found".to_string(),
-        }))
+        None
+    };
+
+    if let Some(ticket) = state.tickets.write().await.get_mut(id) {
+        ticket.restore_ready_at = next_ready_at;
+    }
+    let _ = state.database.set_restore_ready_at(id, next_ready_at).await;
+
+    match next_ready_at {
+        Some(ready_at) => Err(DownloadError::Restoring(ready_at)),
+        None => Ok(()),
     }
 }
 
-// Clean up expired links
-async fn cleanup_expired_links(
-    _user: AuthUser,
-    State(state): State<AppState>,
-) -> Result<Json<CleanupResponse>, ApiError> {
-    let deleted_count = state
-        .database
-        .delete_expired_links()
+async fn start_restore(
+    state: &AppState,
+    bucket: &str,
+    object_key: &str,
+    endpoint_override: Option<&str>,
+) -> Result<(), String> {
+    let client = state
+        .oss
+        .as_deref()
+        .ok_or_else(|| OssError::MissingEndpoint.to_string())?;
+    client
+        .restore_object(
+            bucket,
+            object_key,
+            endpoint_override,
+            state.config.oss_restore_days,
+            state.config.oss_restore_tier,
+        )
         .await
-        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?;
+        .map_err(|e| e.to_string())
+}
 
-    // Also clean up expired tickets in memory
-    let now = Utc::now();
-    let mut tickets = state.tickets.write().await;
-    tickets.retain(|_, ticket| {
-        let not_time_expired = now <= ticket.expires_at;
-        let not_download_exceeded = ticket
-            .max_downloads
-            .map_or(true, |max| ticket.downloads_served < max);
-        not_time_expired && not_download_exceeded
-    });
+// Both UUID links and newer short IDs live in the same ticket map
+async fn resolve_ticket_id(state: &AppState, id_or_slug: String) -> String {
+    match state.slugs.read().await.get(&id_or_slug) {
+        Some(id) => id.clone(),
+        None => id_or_slug,
+    }
+}
 
-    Ok(Json(CleanupResponse { deleted_count }))
+fn link_not_found() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, "Download link not found".to_string())
 }
 
-async fn list_buckets(
-    _user: AuthUser,
-    State(state): State<AppState>,
-) -> Result<Json<crate::oss_client::ListBucketsResponse>, ApiError> {
-    let client = OssClient::new(state.config.as_ref())
-        .map_err(|e| ApiError::Internal(format!("Failed to create OSS client: {}", e)))?;
+fn check_ticket(ticket: &DownloadTicket, client_ip: IpAddr) -> Result<(), (StatusCode, String)> {
+    // Check if expired
+    if Utc::now() > ticket.expires_at {
+        return Err((StatusCode::GONE, "Download link has expired".to_string()));
+    }
 
-    let response = client
-        .list_buckets()
+    // Check download count limit
+    if let Some(max_downloads) = ticket.max_downloads
+        && ticket.downloads_served >= max_downloads
+    {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            "Download limit exceeded".to_string(),
+        ));
+    }
+
+    // Check client network restrictions
+    if !ticket.allowed_cidrs.is_empty()
+        && !ticket
+            .allowed_cidrs
+            .iter()
+            .any(|net| net.contains(&client_ip))
+    {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Download is not permitted from this network".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Where a counted download is served from
+enum Redemption {
+    /// Signed OSS or CDN URL to send the client to
+    Redirect(String),
+    /// Fetched and marked by the backend; the URL is never revealed
+    Watermarked(WatermarkedDownload),
+}
+
+struct WatermarkedDownload {
+    link_id: String,
+    bucket: String,
+    object_key: String,
+    endpoint_override: Option<String>,
+    file_name: String,
+    disposition: DispositionType,
+    recipient: String,
+    mode: WatermarkMode,
+}
+
+/// Count one download and decide how it is served
+async fn redeem_download(
+    state: &AppState,
+    id: &str,
+    client_ip: IpAddr,
+) -> Result<Redemption, (StatusCode, String)> {
+    let signed_url = {
+        // Check and count under one lock so concurrent requests cannot overshoot the limit
+        let mut tickets = state.tickets.write().await;
+        let ticket = tickets.get_mut(id).ok_or_else(link_not_found)?;
+        check_ticket(ticket, client_ip)?;
+
+        if let (Some(mode), Some(recipient)) = (ticket.watermark, ticket.recipient.clone()) {
+            let bucket = ticket
+                .bucket_override
+                .clone()
+                .or_else(|| state.config.aliyun_default_bucket.clone())
+                .ok_or_else(|| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "No bucket configured".to_string(),
+                    )
+                })?;
+            let file_name = ticket
+                .download_filename
+                .clone()
+                .filter(|name| !name.trim().is_empty())
+                .or_else(|| ticket.object_key.rsplit('/').next().map(str::to_string))
+                .unwrap_or_else(|| ticket.object_key.clone());
+
+            let download = WatermarkedDownload {
+                link_id: id.to_string(),
+                bucket,
+                object_key: ticket.object_key.clone(),
+                endpoint_override: ticket.endpoint_override.clone(),
+                file_name,
+                disposition: ticket.disposition.unwrap_or(DispositionType::Attachment),
+                recipient,
+                mode,
+            };
+            // Reserved here, recorded once the marked copy is ready
+            ticket.downloads_served += 1;
+            return Ok(Redemption::Watermarked(download));
+        }
+
+        let source_ip = ticket
+            .bind_client_ip
+            .then(|| source_ip_network(client_ip, state.config.oss_source_ip_prefix_len));
+        let intranet = ticket.use_intranet.unwrap_or_else(|| {
+            state
+                .config
+                .oss_internal_networks
+                .iter()
+                .any(|network| network.contains(&client_ip))
+        });
+
+        // CDN signatures cannot carry a source IP or response overrides, and their
+        // validity comes from the console, so those downloads, links expiring sooner
+        // and internal downloads still go straight to OSS
+        let now = Utc::now();
+        let cdn = cdn_domain(state, ticket).filter(|cdn| {
+            !intranet
+                && source_ip.is_none()
+                && !ticket.overrides_response()
+                && !cdn.outlives(ticket.expires_at, now)
+        });
+
+        let signed_url = match cdn {
+            Some(cdn) => cdn.signed_url(&ticket.object_key, now),
+            None => {
+                build_signed_url(
+                    &state.config,
+                    &SignParams {
+                        bucket_override: ticket.bucket_override.as_deref(),
+                        object_key: &ticket.object_key,
+                        expires_at: ticket.expires_at,
+                        download_filename: ticket.download_filename.as_deref(),
+                        disposition: ticket.disposition,
+                        endpoint_override: ticket.endpoint_override.as_deref(),
+                        source_ip,
+                        intranet,
+                        process: ticket.process.as_deref(),
+                        response_content_type: ticket.response_content_type.as_deref(),
+                        response_cache_control: ticket.response_cache_control.as_deref(),
+                        response_content_language: ticket.response_content_language.as_deref(),
+                    },
+                )
+                .map_err(|_| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Failed to generate download URL".to_string(),
+                    )
+                })?
+                .url
+            }
+        };
+
+        ticket.downloads_served += 1;
+        signed_url
+    };
+
+    // Update download count in database
+    let _ = state.database.increment_downloads(id).await;
+    state.metrics.download_served();
+
+    Ok(Redemption::Redirect(signed_url))
+}
+
+/// A download counted in memory before it is served, given back if serving fails
+/// or the request goes away first
+struct DownloadReservation {
+    tickets: Arc<RwLock<HashMap<String, DownloadTicket>>>,
+    link_id: String,
+    delivered: bool,
+}
+
+impl Drop for DownloadReservation {
+    fn drop(&mut self) {
+        if self.delivered {
+            return;
+        }
+        fn refund(tickets: &mut HashMap<String, DownloadTicket>, link_id: &str) {
+            if let Some(ticket) = tickets.get_mut(link_id) {
+                ticket.downloads_served = ticket.downloads_served.saturating_sub(1);
+            }
+        }
+        if let Ok(mut tickets) = self.tickets.try_write() {
+            refund(&mut tickets, &self.link_id);
+            return;
+        }
+        let tickets = self.tickets.clone();
+        let link_id = std::mem::take(&mut self.link_id);
+        tokio::spawn(async move { refund(&mut *tickets.write().await, &link_id) });
+    }
+}
+
+/// Fetch the object and stream back a copy marked with the link's recipient
+async fn serve_watermarked(
+    state: &AppState,
+    download: WatermarkedDownload,
+) -> Result<Response, DownloadError> {
+    let mut reservation = DownloadReservation {
+        tickets: state.tickets.clone(),
+        link_id: download.link_id.clone(),
+        delivered: false,
+    };
+
+    let client = state.oss.as_deref().ok_or_else(|| {
+        DownloadError::Rejected(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Storage is unavailable".to_string(),
+        )
+    })?;
+    // Held from the fetch until the rewrite finishes, even if the request is dropped
+    let permit = state
+        .watermark_jobs
+        .clone()
+        .try_acquire_owned()
+        .map_err(|_| {
+            DownloadError::Rejected(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Too many watermarked downloads are being prepared, try again shortly".to_string(),
+            )
+        })?;
+
+    let object = client
+        .get_object(
+            &ObjectLocation {
+                bucket: &download.bucket,
+                object_key: &download.object_key,
+                endpoint_override: download.endpoint_override.as_deref(),
+            },
+            state.config.watermark_max_bytes,
+        )
+        .await
+        .map_err(|e| {
+            let status = match e {
+                OssError::TooLarge(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                _ => StatusCode::BAD_GATEWAY,
+            };
+            DownloadError::Rejected(status, format!("Failed to fetch file: {}", e))
+        })?;
+    let Some(pdf) = object else {
+        return Err(DownloadError::Rejected(
+            StatusCode::NOT_FOUND,
+            "File not found".to_string(),
+        ));
+    };
+
+    // Rewriting a large PDF is CPU-bound
+    let marked = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        watermark::watermark_pdf(
+            &pdf,
+            download.mode,
+            &Watermark {
+                recipient: &download.recipient,
+                link_id: &download.link_id,
+                issued_at: Utc::now(),
+            },
+        )
+    })
+    .await
+    .map_err(|e| DownloadError::Rejected(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map_err(|e| {
+        DownloadError::Rejected(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to watermark file: {}", e),
+        )
+    })?;
+
+    reservation.delivered = true;
+    let _ = state
+        .database
+        .increment_downloads(&reservation.link_id)
+        .await;
+    state.metrics.download_served();
+
+    let disposition = content_disposition(download.disposition, Some(&download.file_name));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+            // Each copy names its recipient; shared caches must not hand it to anyone else
+            (header::CACHE_CONTROL, "private, no-store".to_string()),
+        ],
+        marked,
+    )
+        .into_response())
+}
+
+async fn render_landing_page(
+    state: &AppState,
+    ticket: &DownloadTicket,
+    fetch_metadata: bool,
+) -> impl IntoResponse {
+    let bucket = ticket
+        .bucket_override
+        .clone()
+        .or_else(|| state.config.aliyun_default_bucket.clone())
+        .filter(|_| fetch_metadata);
+
+    // The page still renders, without size and date, if OSS is slow or unreachable
+    let metadata = match bucket {
+        Some(bucket) => head_object(
+            state,
+            &bucket,
+            &ticket.object_key,
+            ticket.endpoint_override.as_deref(),
+        )
+        .await
+        .ok()
+        .flatten(),
+        None => None,
+    };
+
+    let file_name = ticket
+        .download_filename
+        .as_deref()
+        .filter(|name| !name.trim().is_empty())
+        .or_else(|| ticket.object_key.rsplit('/').next())
+        .unwrap_or(&ticket.object_key);
+
+    let page = LandingPage {
+        file_name,
+        metadata: metadata.as_ref(),
+        expires_at: ticket.expires_at,
+        remaining_downloads: ticket
+            .max_downloads
+            .map(|max| max.saturating_sub(ticket.downloads_served)),
+    };
+
+    ([(header::CACHE_CONTROL, "no-store")], Html(page.render()))
+}
+
+// HEAD lookups run inline with user requests, so a slow OSS must not stall them
+const OBJECT_HEAD_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+async fn head_object(
+    state: &AppState,
+    bucket: &str,
+    object_key: &str,
+    endpoint_override: Option<&str>,
+) -> Result<Option<ObjectMetadata>, String> {
+    let client = state
+        .oss
+        .as_deref()
+        .ok_or_else(|| OssError::MissingEndpoint.to_string())?;
+    tokio::time::timeout(
+        OBJECT_HEAD_TIMEOUT,
+        client.head_object(bucket, object_key, endpoint_override),
+    )
+    .await
+    .map_err(|_| "OSS did not respond in time".to_string())?
+    .map_err(|e| e.to_string())
+}
+
+// Links with a slug are always published under it
+fn public_link_url(config: &AppConfig, id: &str, slug: Option<&str>) -> String {
+    format!("{}{}", config.download_base_url(), slug.unwrap_or(id))
+}
+
+// Attempts before giving up on finding a free short ID
+const LINK_ID_ATTEMPTS: usize = 5;
+
+async fn generate_link_id(state: &AppState) -> Result<String, ApiError> {
+    let format = state.config.link_id_format;
+    if !format.needs_collision_check() {
+        return Ok(format.generate());
+    }
+
+    for _ in 0..LINK_ID_ATTEMPTS {
+        let id = format.generate();
+        if !identifier_in_use(state, &id).await? {
+            return Ok(id);
+        }
+    }
+
+    Err(ApiError::Internal(
+        "Could not allocate a unique link ID".to_string(),
+    ))
+}
+
+async fn identifier_in_use(state: &AppState, value: &str) -> Result<bool, ApiError> {
+    state
+        .database
+        .identifier_in_use(value)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))
+}
+
+// Words that would be confusing or collide with routes if used as a slug
+const RESERVED_SLUGS: &[&str] = &[
+    "admin",
+    "api",
+    "assets",
+    "download",
+    "downloads",
+    "favicon.ico",
+    "healthz",
+    "links",
+    "login",
+    "logout",
+    "metrics",
+    "new",
+    "qr",
+    "robots.txt",
+    "sign",
+    "static",
+];
+
+/// Blank means unset; values end up in response headers or documents, so no control characters
+fn text_option(field: &str, value: Option<String>) -> Result<Option<String>, ApiError> {
+    let Some(value) = value.map(|value| value.trim().to_string()) else {
+        return Ok(None);
+    };
+    if value.chars().any(char::is_control) {
+        return Err(ApiError::BadRequest(format!(
+            "{} must not contain control characters",
+            field
+        )));
+    }
+    Ok(Some(value).filter(|value| !value.is_empty()))
+}
+
+fn validate_slug(slug: &str) -> Result<(), ApiError> {
+    if !(3..=64).contains(&slug.len()) {
+        return Err(ApiError::BadRequest(
+            "Slug must be between 3 and 64 characters".to_string(),
+        ));
+    }
+    if !slug
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(ApiError::BadRequest(
+            "Slug may only contain letters, digits, '-', '_' and '.'".to_string(),
+        ));
+    }
+    if !slug.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return Err(ApiError::BadRequest(
+            "Slug must start with a letter or digit".to_string(),
+        ));
+    }
+    // A UUID-shaped slug would shadow, or be shadowed by, a link ID
+    if Uuid::parse_str(slug).is_ok() || RESERVED_SLUGS.contains(&slug.to_ascii_lowercase().as_str())
+    {
+        return Err(ApiError::BadRequest(format!("Slug '{}' is reserved", slug)));
+    }
+    Ok(())
+}
+
+// IPv4 clients are bound to their configured subnet, IPv6 clients to their exact address
+fn source_ip_network(ip: IpAddr, ipv4_prefix_len: u8) -> IpNet {
+    let prefix_len = match ip {
+        IpAddr::V4(_) => ipv4_prefix_len,
+        IpAddr::V6(_) => 128,
+    };
+    IpNet::new(ip, prefix_len).unwrap_or_else(|_| IpNet::from(ip))
+}
+
+// Accept bare addresses as single-host networks
+fn parse_cidr(value: &str) -> Result<IpNet, ApiError> {
+    let value = value.trim();
+    value
+        .parse::<IpNet>()
+        .or_else(|_| value.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|_| ApiError::BadRequest(format!("Invalid CIDR: {}", value)))
+}
+
+// Get links list
+async fn list_links(
+    _user: AuthUser,
+    Query(params): Query<ListLinksQuery>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let links = state
+        .database
+        .list_download_links(params.limit, params.offset)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?;
+
+    let download_links: Vec<DownloadLinkResponse> = links
+        .into_iter()
+        .map(|link| DownloadLinkResponse::from_link(link, &state.config))
+        .collect();
+
+    let response = Json(ListLinksResponse {
+        total: download_links.len(),
+        links: download_links,
+    });
+
+    let mut response = response.into_response();
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("no-store, no-cache, must-revalidate"),
+    );
+    response
+        .headers_mut()
+        .insert(header::PRAGMA, HeaderValue::from_static("no-cache"));
+    response
+        .headers_mut()
+        .insert(header::EXPIRES, HeaderValue::from_static("0"));
+
+    Ok(response)
+}
+
+// Get single link info
+async fn get_link_info(
+    _user: AuthUser,
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<DownloadLinkResponse>, ApiError> {
+    let link = find_download_link(&state, &id).await?;
+
+    Ok(Json(DownloadLinkResponse::from_link(link, &state.config)))
+}
+
+// QR code of a link's public URL, for printing on labels and manifests
+async fn get_link_qr(
+    _user: AuthUser,
+    Path(id): Path<String>,
+    Query(options): Query<QrOptions>,
+    State(state): State<AppState>,
+) -> Result<Response, ApiError> {
+    let link = find_download_link(&state, &id).await?;
+
+    let url = public_link_url(&state.config, &link.id, link.slug.as_deref());
+    let image = render_qr(&url, &options)?;
+
+    let disposition = format!(
+        "inline; filename=\"{}.{}\"",
+        link.slug.as_deref().unwrap_or(&link.id),
+        image.extension
+    );
+    let mut response = image.bytes.into_response();
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(image.content_type),
+    );
+    if let Ok(value) = HeaderValue::from_str(&disposition) {
+        headers.insert(header::CONTENT_DISPOSITION, value);
+    }
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+
+    Ok(response)
+}
+
+fn render_qr(url: &str, options: &QrOptions) -> Result<QrImage, ApiError> {
+    qr::render(url, options).map_err(|e| match e {
+        QrError::InvalidSize(_) | QrError::Encode(_) => ApiError::BadRequest(e.to_string()),
+        QrError::Png(_) => ApiError::Internal(e.to_string()),
+    })
+}
+
+// Delete link
+async fn delete_link(
+    _user: AuthUser,
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<DeleteResponse>, ApiError> {
+    let link = state
+        .database
+        .get_download_link(&id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?;
+
+    let deleted = match &link {
+        Some(link) => state
+            .database
+            .delete_download_link(&link.id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?,
+        None => false,
+    };
+
+    if let Some(link) = link.filter(|_| deleted) {
+        // Also remove from memory
+        state.tickets.write().await.remove(&link.id);
+        if let Some(slug) = &link.slug {
+            state.slugs.write().await.remove(slug);
+        }
+
+        Ok(Json(DeleteResponse {
+            success: true,
+            message: "Link deleted successfully".to_string(),
+        }))
+    } else {
+        Ok(Json(DeleteResponse {
+            success: false,
+            message: "Link not found".to_string(),
+        }))
+    }
+}
+
+// Clean up expired links
+async fn cleanup_expired_links(
+    _user: AuthUser,
+    State(state): State<AppState>,
+) -> Result<Json<CleanupResponse>, ApiError> {
+    let deleted_count = state
+        .database
+        .delete_expired_links()
+        .await
+        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?;
+
+    // Also clean up expired tickets in memory
+    let now = Utc::now();
+    let mut tickets = state.tickets.write().await;
+    tickets.retain(|_, ticket| {
+        let not_time_expired = now <= ticket.expires_at;
+        let not_download_exceeded = ticket
+            .max_downloads
+            .is_none_or(|max| ticket.downloads_served < max);
+        not_time_expired && not_download_exceeded
+    });
+    state
+        .slugs
+        .write()
+        .await
+        .retain(|_, id| tickets.contains_key(id));
+
+    Ok(Json(CleanupResponse { deleted_count }))
+}
+
+async fn list_buckets(
+    _user: AuthUser,
+    State(state): State<AppState>,
+) -> Result<Json<crate::oss_client::ListBucketsResponse>, ApiError> {
+    let client = oss_client(&state)?;
+
+    let response = client
+        .list_buckets()
         .await
         .map_err(|e| ApiError::Internal(format!("Failed to list buckets: {}", e)))?;
 
-    Ok(Json(response))
+    Ok(Json(response))
+}
+
+#[derive(Debug, Serialize)]
+pub struct OssStatsResponse {
+    pub bucket_location_cache: CacheStats,
+}
+
+async fn oss_stats(
+    _user: AuthUser,
+    State(state): State<AppState>,
+) -> Result<Json<OssStatsResponse>, ApiError> {
+    let client = oss_client(&state)?;
+    Ok(Json(OssStatsResponse {
+        bucket_location_cache: client.bucket_cache_stats(),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListObjectsQuery {
+    pub bucket: String,
+    pub prefix: Option<String>,
+    pub delimiter: Option<String>,
+    #[serde(rename = "continuation-token")]
+    pub continuation_token: Option<String>,
+    #[serde(rename = "start-after")]
+    pub start_after: Option<String>,
+    #[serde(rename = "max-keys")]
+    pub max_keys: Option<u32>,
+    /// OSS always pages in key order, so other orders only apply within a page
+    #[serde(default)]
+    pub sort: ObjectSort,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectSort {
+    #[default]
+    Key,
+    LastModified,
+    Size,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+const MAX_LIST_KEYS: u32 = 1000;
+
+async fn list_objects(
+    _user: AuthUser,
+    State(state): State<AppState>,
+    Query(query): Query<ListObjectsQuery>,
+) -> Result<Json<crate::oss_client::ListObjectsResponse>, ApiError> {
+    if query.bucket.is_empty() {
+        return Err(ApiError::BadRequest("Bucket name is required".to_string()));
+    }
+
+    let client = oss_client(&state)?;
+
+    let max_keys = query.max_keys.unwrap_or(MAX_LIST_KEYS);
+    if !(1..=MAX_LIST_KEYS).contains(&max_keys) {
+        return Err(ApiError::BadRequest(format!(
+            "max-keys must be between 1 and {}",
+            MAX_LIST_KEYS
+        )));
+    }
+
+    let mut response = client
+        .list_objects(
+            &query.bucket,
+            &ListObjectsParams {
+                prefix: query.prefix.as_deref(),
+                delimiter: query.delimiter.as_deref().filter(|d| !d.is_empty()),
+                continuation_token: query.continuation_token.as_deref(),
+                start_after: query.start_after.as_deref(),
+                max_keys: Some(max_keys),
+            },
+        )
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to list objects: {}", e)))?;
+
+    match query.sort {
+        ObjectSort::Key => {}
+        // RFC 3339 timestamps from OSS sort correctly as strings
+        ObjectSort::LastModified => response
+            .objects
+            .sort_by(|a, b| a.last_modified.cmp(&b.last_modified)),
+        ObjectSort::Size => response.objects.sort_by_key(|object| object.size),
+    }
+    if let SortOrder::Desc = query.order {
+        response.objects.reverse();
+        response.common_prefixes.reverse();
+    }
+
+    Ok(Json(response))
+}
+
+const SEARCH_PAGE_SIZE: u64 = 1000;
+
+/// Walk the listing under a prefix and stream matching objects back as NDJSON
+async fn search_objects(
+    _user: AuthUser,
+    State(state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Response, ApiError> {
+    if query.bucket.is_empty() {
+        return Err(ApiError::BadRequest("Bucket name is required".to_string()));
+    }
+    let filter = SearchFilter::new(&query).map_err(ApiError::BadRequest)?;
+    let client = state.oss.clone().ok_or_else(oss_unavailable)?;
+
+    let server_cap = state.config.search_max_scanned_keys;
+    let max_scan = query.max_scan.unwrap_or(server_cap).clamp(1, server_cap);
+
+    // Lines are produced by a separate task so a slow reader applies backpressure
+    // to the listing, and a disconnect stops it
+    let (sender, receiver) = tokio::sync::mpsc::channel(64);
+    tokio::spawn(run_search(client, query, filter, max_scan, sender));
+
+    let body = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        let event = receiver.recv().await?;
+        let mut line = serde_json::to_vec(&event).unwrap_or_default();
+        line.push(b'\n');
+        Some((Ok::<_, Infallible>(Bytes::from(line)), receiver))
+    });
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+async fn run_search(
+    client: Arc<OssClient>,
+    query: SearchQuery,
+    filter: SearchFilter,
+    max_scan: u64,
+    sender: tokio::sync::mpsc::Sender<SearchEvent>,
+) {
+    let mut scanned = 0;
+    let mut matched = 0;
+    let mut continuation_token: Option<String> = None;
+    let mut last_key: Option<String> = None;
+
+    loop {
+        // Never list more keys than the scan budget has left
+        let page_size = SEARCH_PAGE_SIZE.min(max_scan - scanned) as u32;
+        let page = match client
+            .list_objects(
+                &query.bucket,
+                &ListObjectsParams {
+                    prefix: query.prefix.as_deref(),
+                    delimiter: None,
+                    continuation_token: continuation_token.as_deref(),
+                    // Only needed for the first page; the token carries on from there
+                    start_after: query
+                        .start_after
+                        .as_deref()
+                        .filter(|_| continuation_token.is_none()),
+                    max_keys: Some(page_size),
+                },
+            )
+            .await
+        {
+            Ok(page) => page,
+            Err(e) => {
+                let message = format!("Failed to list objects: {}", e);
+                sender.send(SearchEvent::Error { message }).await.ok();
+                return;
+            }
+        };
+
+        let page_len = page.objects.len();
+        for (index, object) in page.objects.into_iter().enumerate() {
+            scanned += 1;
+            last_key = Some(object.key.clone());
+            if !filter.matches(&object) {
+                continue;
+            }
+
+            matched += 1;
+            if sender.send(SearchEvent::Object(object)).await.is_err() {
+                // The client went away
+                return;
+            }
+            if query.limit.is_some_and(|limit| matched >= limit) {
+                let more = index + 1 < page_len || page.is_truncated;
+                let done = SearchEvent::Done {
+                    scanned,
+                    matched,
+                    truncated: more,
+                    resume_after: last_key.filter(|_| more),
+                };
+                sender.send(done).await.ok();
+                return;
+            }
+        }
+
+        if !page.is_truncated || scanned >= max_scan {
+            let done = SearchEvent::Done {
+                scanned,
+                matched,
+                truncated: page.is_truncated,
+                resume_after: last_key.filter(|_| page.is_truncated),
+            };
+            sender.send(done).await.ok();
+            return;
+        }
+        continuation_token = page.next_continuation_token;
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ObjectMetadataQuery {
+    pub bucket: String,
+    pub key: String,
+    pub endpoint: Option<String>,
+}
+
+async fn get_object_metadata(
+    _user: AuthUser,
+    State(state): State<AppState>,
+    Query(query): Query<ObjectMetadataQuery>,
+) -> Result<Json<ObjectMetadata>, ApiError> {
+    if query.bucket.is_empty() || query.key.is_empty() {
+        return Err(ApiError::BadRequest(
+            "Bucket and key are required".to_string(),
+        ));
+    }
+
+    let client = oss_client(&state)?;
+
+    let metadata = client
+        .head_object(&query.bucket, &query.key, query.endpoint.as_deref())
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to fetch object metadata: {}", e)))?
+        .ok_or_else(|| ApiError::BadRequest(format!("Object '{}' not found", query.key)))?;
+
+    Ok(Json(metadata))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteObjectQuery {
+    pub bucket: Option<String>,
+    pub key: String,
+    pub endpoint: Option<String>,
+    /// Also delete every download link pointing at the object
+    pub revoke_links: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeleteObjectResponse {
+    pub bucket: String,
+    pub object_key: String,
+    pub revoked_links: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CopyObjectRequest {
+    pub source_bucket: Option<String>,
+    pub source_key: String,
+    /// Defaults to the source bucket; both buckets must be in the same region
+    pub destination_bucket: Option<String>,
+    pub destination_key: String,
+    pub endpoint: Option<String>,
+    /// Replace an existing destination object instead of refusing with 409
+    pub overwrite: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RenameObjectRequest {
+    #[serde(flatten)]
+    pub copy: CopyObjectRequest,
+    /// Delete the download links that pointed at the old key
+    pub revoke_links: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CopyObjectResponse {
+    pub bucket: String,
+    pub object_key: String,
+    pub size: u64,
+    pub etag: Option<String>,
+    pub revoked_links: Vec<String>,
+}
+
+// CopyObject only accepts sources up to 1 GiB; anything larger is copied part by part
+const COPY_OBJECT_LIMIT: u64 = 1024 * MIB;
+const COPY_PART_SIZE: u64 = 256 * MIB;
+
+async fn delete_object(
+    _user: AuthUser,
+    State(state): State<AppState>,
+    Query(query): Query<DeleteObjectQuery>,
+) -> Result<Json<DeleteObjectResponse>, ApiError> {
+    let bucket = request_bucket(&state.config, query.bucket)?;
+    if query.key.is_empty() {
+        return Err(ApiError::BadRequest("Object key is required".to_string()));
+    }
+
+    let client = oss_client(&state)?;
+
+    // Revoked before the delete so no link can outlive the object it points at
+    let revoked_links = if query.revoke_links.unwrap_or(false) {
+        revoke_object_links(&state, &bucket, &query.key).await?
+    } else {
+        Vec::new()
+    };
+
+    client
+        .delete_object(&bucket, &query.key, query.endpoint.as_deref())
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to delete object: {}", e)))?;
+
+    Ok(Json(DeleteObjectResponse {
+        bucket,
+        object_key: query.key,
+        revoked_links,
+    }))
+}
+
+async fn copy_object(
+    _user: AuthUser,
+    State(state): State<AppState>,
+    Json(payload): Json<CopyObjectRequest>,
+) -> Result<Json<CopyObjectResponse>, ApiError> {
+    let client = oss_client(&state)?;
+    let (_, copied) = server_side_copy(&state, client, payload).await?;
+    Ok(Json(copied))
+}
+
+/// Copy to the new key, then delete the old one
+async fn rename_object(
+    _user: AuthUser,
+    State(state): State<AppState>,
+    Json(payload): Json<RenameObjectRequest>,
+) -> Result<Json<CopyObjectResponse>, ApiError> {
+    let client = oss_client(&state)?;
+    let endpoint = payload.copy.endpoint.clone();
+    let source_key = payload.copy.source_key.clone();
+    let (source_bucket, mut renamed) = server_side_copy(&state, client, payload.copy).await?;
+
+    if payload.revoke_links.unwrap_or(false) {
+        renamed.revoked_links = revoke_object_links(&state, &source_bucket, &source_key).await?;
+    }
+
+    client
+        .delete_object(&source_bucket, &source_key, endpoint.as_deref())
+        .await
+        .map_err(|e| {
+            ApiError::Internal(format!(
+                "Copied to '{}' but failed to delete '{}': {}",
+                renamed.object_key, source_key, e
+            ))
+        })?;
+
+    Ok(Json(renamed))
+}
+
+/// Returns the source bucket alongside the copied object
+async fn server_side_copy(
+    state: &AppState,
+    client: &OssClient,
+    payload: CopyObjectRequest,
+) -> Result<(String, CopyObjectResponse), ApiError> {
+    let source_bucket = request_bucket(&state.config, payload.source_bucket)?;
+    let destination_bucket = payload
+        .destination_bucket
+        .filter(|bucket| !bucket.is_empty())
+        .unwrap_or_else(|| source_bucket.clone());
+    if payload.source_key.is_empty() || payload.destination_key.is_empty() {
+        return Err(ApiError::BadRequest(
+            "Source and destination keys are required".to_string(),
+        ));
+    }
+    if source_bucket == destination_bucket && payload.source_key == payload.destination_key {
+        return Err(ApiError::BadRequest(
+            "Source and destination are the same object".to_string(),
+        ));
+    }
+
+    let endpoint = payload.endpoint.as_deref();
+    let source = ObjectLocation {
+        bucket: &source_bucket,
+        object_key: &payload.source_key,
+        endpoint_override: endpoint,
+    };
+    let destination = ObjectLocation {
+        bucket: &destination_bucket,
+        object_key: &payload.destination_key,
+        endpoint_override: endpoint,
+    };
+
+    let metadata = head_object(state, &source_bucket, &payload.source_key, endpoint)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to check object: {}", e)))?
+        .ok_or_else(|| {
+            ApiError::NotFound(format!(
+                "Object '{}' does not exist in bucket '{}'",
+                payload.source_key, source_bucket
+            ))
+        })?;
+
+    let forbid_overwrite = !payload.overwrite.unwrap_or(false);
+    if forbid_overwrite {
+        ensure_object_absent(
+            state,
+            &destination_bucket,
+            &payload.destination_key,
+            endpoint,
+        )
+        .await?;
+    }
+
+    if metadata.size <= COPY_OBJECT_LIMIT {
+        client
+            .copy_object(&source, &destination, forbid_overwrite)
+            .await
+            .map_err(|e| ApiError::Internal(format!("Failed to copy object: {}", e)))?;
+    } else {
+        let content_type = metadata
+            .content_type
+            .as_deref()
+            .unwrap_or("application/octet-stream");
+        let upload_id = client
+            .initiate_multipart_upload(&destination, content_type, forbid_overwrite)
+            .await
+            .map_err(|e| ApiError::Internal(format!("Failed to start multipart copy: {}", e)))?;
+
+        let part_size = COPY_PART_SIZE.max(metadata.size.div_ceil(MAX_PARTS));
+        let mut parts = Vec::new();
+        let mut first = 0;
+        while first < metadata.size {
+            let last = (first + part_size).min(metadata.size) - 1;
+            let part_number = parts.len() as u32 + 1;
+            match client
+                .upload_part_copy(
+                    &source,
+                    &destination,
+                    &upload_id,
+                    part_number,
+                    (first, last),
+                )
+                .await
+            {
+                Ok(etag) => parts.push(CompletedPart { part_number, etag }),
+                Err(e) => {
+                    // Best effort: do not leave billed parts behind
+                    client
+                        .abort_multipart_upload(&destination, &upload_id)
+                        .await
+                        .ok();
+                    return Err(ApiError::Internal(format!(
+                        "Failed to copy part {}: {}",
+                        part_number, e
+                    )));
+                }
+            }
+            first = last + 1;
+        }
+
+        client
+            .complete_multipart_upload(&destination, &upload_id, &parts, forbid_overwrite)
+            .await
+            .map_err(|e| ApiError::Internal(format!("Failed to complete multipart copy: {}", e)))?;
+    }
+
+    let etag = head_object(
+        state,
+        &destination_bucket,
+        &payload.destination_key,
+        endpoint,
+    )
+    .await
+    .ok()
+    .flatten()
+    .and_then(|metadata| metadata.etag);
+
+    Ok((
+        source_bucket,
+        CopyObjectResponse {
+            bucket: destination_bucket,
+            object_key: payload.destination_key,
+            size: metadata.size,
+            etag,
+            revoked_links: Vec::new(),
+        },
+    ))
+}
+
+fn oss_client(state: &AppState) -> Result<&OssClient, ApiError> {
+    state.oss.as_deref().ok_or_else(oss_unavailable)
+}
+
+fn oss_unavailable() -> ApiError {
+    ApiError::Internal(format!("OSS is unavailable: {}", OssError::MissingEndpoint))
+}
+
+fn request_bucket(config: &AppConfig, bucket: Option<String>) -> Result<String, ApiError> {
+    bucket
+        .filter(|bucket| !bucket.is_empty())
+        .or_else(|| config.aliyun_default_bucket.clone())
+        .ok_or_else(|| ApiError::BadRequest("Bucket name is required".to_string()))
+}
+
+async fn ensure_object_absent(
+    state: &AppState,
+    bucket: &str,
+    object_key: &str,
+    endpoint: Option<&str>,
+) -> Result<(), ApiError> {
+    match head_object(state, bucket, object_key, endpoint).await {
+        Ok(Some(_)) => Err(ApiError::Conflict(format!(
+            "Object '{}' already exists in bucket '{}'",
+            object_key, bucket
+        ))),
+        // OSS still refuses the overwrite itself if the lookup failed
+        _ => Ok(()),
+    }
+}
+
+/// Remove links from the database and from memory so they stop resolving at once
+async fn revoke_object_links(
+    state: &AppState,
+    bucket: &str,
+    object_key: &str,
+) -> Result<Vec<String>, ApiError> {
+    let revoked = state
+        .database
+        .delete_links_for_object(
+            bucket,
+            object_key,
+            state.config.aliyun_default_bucket.as_deref(),
+        )
+        .await
+        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?;
+
+    let mut tickets = state.tickets.write().await;
+    let mut slugs = state.slugs.write().await;
+    Ok(revoked
+        .into_iter()
+        .map(|(id, slug)| {
+            tickets.remove(&id);
+            if let Some(slug) = slug {
+                slugs.remove(&slug);
+            }
+            id
+        })
+        .collect())
+}
+
+/// Destination and options for `POST /objects/upload`. They travel in the query string
+/// so the body can be streamed straight through to OSS.
+#[derive(Debug, Deserialize)]
+pub struct UploadObjectQuery {
+    pub bucket: Option<String>,
+    /// Full object key; defaults to `prefix` plus the multipart filename
+    pub key: Option<String>,
+    pub prefix: Option<String>,
+    pub endpoint: Option<String>,
+    pub content_type: Option<String>,
+    /// Replace an existing object instead of refusing with 409; fixed when an upload
+    /// is started, so a resume may only repeat it
+    pub overwrite: Option<bool>,
+    /// Continue an interrupted multipart upload; the body carries the bytes after
+    /// `bytes_uploaded` from `GET /objects/upload/:upload_id`. Refused with 409 while
+    /// another request is still writing to it.
+    pub upload_id: Option<String>,
+    /// Also create a download link for the stored object
+    pub create_link: Option<bool>,
+    pub link_expires_in_seconds: Option<i64>,
+    pub link_max_downloads: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UploadObjectResponse {
+    pub bucket: String,
+    pub object_key: String,
+    pub size: u64,
+    pub etag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<CreateLinkResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PendingUploadResponse {
+    pub upload_id: String,
+    pub bucket: String,
+    pub endpoint: Option<String>,
+    pub object_key: String,
+    pub content_type: String,
+    pub created_at: String,
+    pub part_size: u64,
+    pub bytes_uploaded: u64,
+    pub parts: Vec<UploadedPart>,
+}
+
+impl PendingUploadResponse {
+    fn from_upload(mut upload: PendingUpload) -> Self {
+        drop_short_part(&mut upload);
+        Self {
+            bytes_uploaded: uploaded_bytes(&upload),
+            upload_id: upload.upload_id,
+            bucket: upload.bucket,
+            endpoint: upload.endpoint,
+            object_key: upload.object_key,
+            content_type: upload.content_type,
+            created_at: upload.created_at.to_rfc3339(),
+            part_size: MIN_PART_SIZE,
+            parts: upload.parts,
+        }
+    }
+}
+
+/// Upload a multipart/form-data `file` field or a raw request body to OSS
+async fn upload_object(
+    _user: AuthUser,
+    State(state): State<AppState>,
+    Query(query): Query<UploadObjectQuery>,
+    request: Request,
+) -> Result<Json<UploadObjectResponse>, ApiError> {
+    let is_form = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("multipart/form-data"));
+
+    if is_form {
+        let mut multipart = Multipart::from_request(request, &state)
+            .await
+            .map_err(|e| ApiError::BadRequest(e.body_text()))?;
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|e| ApiError::BadRequest(format!("Invalid form data: {}", e)))?
+        {
+            if field.name() != Some("file") {
+                continue;
+            }
+            let filename = field.file_name().map(str::to_string);
+            let content_type = field.content_type().map(str::to_string);
+            return store_upload(&state, query, filename, content_type, field).await;
+        }
+        return Err(ApiError::BadRequest(
+            "Form data has no 'file' field".to_string(),
+        ));
+    }
+
+    let content_type = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body = request.into_body().into_data_stream();
+    store_upload(&state, query, None, content_type, body).await
+}
+
+async fn store_upload<S, E>(
+    state: &AppState,
+    query: UploadObjectQuery,
+    filename: Option<String>,
+    content_type: Option<String>,
+    body: S,
+) -> Result<Json<UploadObjectResponse>, ApiError>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: std::fmt::Display,
+{
+    let client = oss_client(state)?;
+
+    let mut upload = match &query.upload_id {
+        Some(upload_id) => {
+            let claim = claim_upload(state, upload_id)?;
+            let mut pending = find_pending_upload(state, upload_id).await?;
+            if query
+                .overwrite
+                .is_some_and(|overwrite| overwrite == pending.forbid_overwrite)
+            {
+                return Err(ApiError::BadRequest(
+                    "overwrite must match the value the upload was started with".to_string(),
+                ));
+            }
+            if let Some(part_number) = drop_short_part(&mut pending) {
+                state
+                    .database
+                    .delete_upload_parts_from(&pending.upload_id, part_number)
+                    .await
+                    .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?;
+            }
+            StreamedUpload {
+                state,
+                client,
+                bytes_uploaded: uploaded_bytes(&pending),
+                next_part: pending.parts.last().map_or(1, |part| part.part_number + 1),
+                upload_id: Some(pending.upload_id),
+                claim: Some(claim),
+                bucket: pending.bucket,
+                endpoint: pending.endpoint,
+                object_key: pending.object_key,
+                content_type: pending.content_type,
+                forbid_overwrite: pending.forbid_overwrite,
+            }
+        }
+        None => {
+            let bucket = request_bucket(&state.config, query.bucket.clone())?;
+            let object_key = match (&query.key, &filename) {
+                (Some(key), _) if !key.is_empty() => key.clone(),
+                (_, Some(filename)) => {
+                    let filename = sanitize_upload_filename(filename)?;
+                    match query
+                        .prefix
+                        .as_deref()
+                        .map(|prefix| prefix.trim_matches('/'))
+                    {
+                        Some(prefix) if !prefix.is_empty() => format!("{}/{}", prefix, filename),
+                        _ => filename,
+                    }
+                }
+                _ => {
+                    return Err(ApiError::BadRequest("Object key is required".to_string()));
+                }
+            };
+
+            let forbid_overwrite = !query.overwrite.unwrap_or(false);
+            if forbid_overwrite {
+                ensure_object_absent(state, &bucket, &object_key, query.endpoint.as_deref())
+                    .await?;
+            }
+
+            StreamedUpload {
+                state,
+                client,
+                upload_id: None,
+                claim: None,
+                bucket,
+                endpoint: query.endpoint.clone(),
+                object_key,
+                content_type: query
+                    .content_type
+                    .clone()
+                    .or(content_type)
+                    .unwrap_or_else(|| "application/octet-stream".to_string()),
+                forbid_overwrite,
+                next_part: 1,
+                bytes_uploaded: 0,
+            }
+        }
+    };
+
+    let etag = upload.write(body).await?;
+
+    let link = if query.create_link.unwrap_or(false) {
+        Some(
+            create_link(
+                state,
+                CreateLinkRequest {
+                    object_key: upload.object_key.clone(),
+                    bucket: Some(upload.bucket.clone()),
+                    expires_in_seconds: query.link_expires_in_seconds.unwrap_or(0),
+                    max_downloads: query.link_max_downloads,
+                    endpoint: upload.endpoint.clone(),
+                    ..Default::default()
+                },
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
+
+    Ok(Json(UploadObjectResponse {
+        bucket: upload.bucket,
+        object_key: upload.object_key,
+        size: upload.bytes_uploaded,
+        etag,
+        link,
+    }))
+}
+
+/// A body being copied to OSS: one PUT if it fits in a single part, otherwise a
+/// multipart upload whose parts are recorded as they land so it can be resumed
+struct StreamedUpload<'a> {
+    state: &'a AppState,
+    client: &'a OssClient,
+    upload_id: Option<String>,
+    /// Held from the moment `upload_id` is known so no other request writes parts to it
+    claim: Option<UploadClaim>,
+    bucket: String,
+    endpoint: Option<String>,
+    object_key: String,
+    content_type: String,
+    forbid_overwrite: bool,
+    next_part: u32,
+    bytes_uploaded: u64,
+}
+
+impl StreamedUpload<'_> {
+    fn location(&self) -> ObjectLocation<'_> {
+        ObjectLocation {
+            bucket: &self.bucket,
+            object_key: &self.object_key,
+            endpoint_override: self.endpoint.as_deref(),
+        }
+    }
+
+    /// Returns the ETag of the stored object
+    async fn write<S, E>(&mut self, body: S) -> Result<Option<String>, ApiError>
+    where
+        S: Stream<Item = Result<Bytes, E>>,
+        E: std::fmt::Display,
+    {
+        let part_size = MIN_PART_SIZE as usize;
+        let mut body = std::pin::pin!(body);
+        let mut buffer = BytesMut::new();
+
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(|e| match &self.upload_id {
+                Some(upload_id) => ApiError::BadRequest(format!(
+                    "Upload interrupted after {} bytes ({}); resume with upload_id {}",
+                    self.bytes_uploaded, e, upload_id
+                )),
+                None => ApiError::BadRequest(format!("Failed to read upload body: {}", e)),
+            })?;
+            buffer.extend_from_slice(&chunk);
+            while buffer.len() >= part_size {
+                let part = buffer.split_to(part_size).freeze();
+                self.upload_part(part).await?;
+            }
+        }
+
+        let Some(upload_id) = self.upload_id.clone() else {
+            let body = buffer.freeze();
+            self.bytes_uploaded = body.len() as u64;
+            return self
+                .client
+                .put_object(
+                    &self.location(),
+                    &self.content_type,
+                    body,
+                    self.forbid_overwrite,
+                )
+                .await
+                .map_err(|e| ApiError::Internal(format!("Failed to upload object: {}", e)));
+        };
+
+        if !buffer.is_empty() {
+            self.upload_part(buffer.freeze()).await?;
+        }
+
+        let parts: Vec<CompletedPart> = find_pending_upload(self.state, &upload_id)
+            .await?
+            .parts
+            .into_iter()
+            .map(|part| CompletedPart {
+                part_number: part.part_number,
+                etag: part.etag,
+            })
+            .collect();
+        if parts.is_empty() {
+            return Err(ApiError::BadRequest("No data to upload".to_string()));
+        }
+
+        self.client
+            .complete_multipart_upload(&self.location(), &upload_id, &parts, self.forbid_overwrite)
+            .await
+            .map_err(|e| ApiError::Internal(format!("Failed to complete upload: {}", e)))?;
+        self.state
+            .database
+            .delete_pending_upload(&upload_id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?;
+
+        let metadata = head_object(
+            self.state,
+            &self.bucket,
+            &self.object_key,
+            self.endpoint.as_deref(),
+        )
+        .await
+        .ok()
+        .flatten();
+        Ok(metadata.and_then(|metadata| metadata.etag))
+    }
+
+    async fn upload_part(&mut self, body: Bytes) -> Result<(), ApiError> {
+        if u64::from(self.next_part) > MAX_PARTS {
+            return Err(ApiError::BadRequest(format!(
+                "Upload exceeds {} parts of {} bytes",
+                MAX_PARTS, MIN_PART_SIZE
+            )));
+        }
+
+        let upload_id = match &self.upload_id {
+            Some(upload_id) => upload_id.clone(),
+            None => {
+                let upload_id = self
+                    .client
+                    .initiate_multipart_upload(
+                        &self.location(),
+                        &self.content_type,
+                        self.forbid_overwrite,
+                    )
+                    .await
+                    .map_err(|e| {
+                        ApiError::Internal(format!("Failed to start multipart upload: {}", e))
+                    })?;
+                self.claim = self.state.claim_upload(&upload_id);
+                self.state
+                    .database
+                    .create_pending_upload(NewPendingUpload {
+                        upload_id: upload_id.clone(),
+                        bucket: self.bucket.clone(),
+                        endpoint: self.endpoint.clone(),
+                        object_key: self.object_key.clone(),
+                        content_type: self.content_type.clone(),
+                        forbid_overwrite: self.forbid_overwrite,
+                    })
+                    .await
+                    .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?;
+                self.upload_id = Some(upload_id.clone());
+                upload_id
+            }
+        };
+
+        let size = body.len();
+        let etag = self
+            .client
+            .upload_part(&self.location(), &upload_id, self.next_part, body)
+            .await
+            .map_err(|e| {
+                ApiError::Internal(format!(
+                    "Failed to upload part {} (resume with upload_id {}): {}",
+                    self.next_part, upload_id, e
+                ))
+            })?;
+        self.state
+            .database
+            .record_upload_part(
+                &upload_id,
+                &UploadedPart {
+                    part_number: self.next_part,
+                    etag,
+                    size: size as i64,
+                },
+            )
+            .await
+            .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?;
+
+        self.next_part += 1;
+        self.bytes_uploaded += size as u64;
+        Ok(())
+    }
+}
+
+async fn get_pending_upload(
+    _user: AuthUser,
+    Path(upload_id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<PendingUploadResponse>, ApiError> {
+    let upload = find_pending_upload(&state, &upload_id).await?;
+    Ok(Json(PendingUploadResponse::from_upload(upload)))
+}
+
+/// Abort an unfinished upload and discard the parts stored in OSS
+async fn abort_pending_upload(
+    _user: AuthUser,
+    Path(upload_id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<DeleteResponse>, ApiError> {
+    let _claim = claim_upload(&state, &upload_id)?;
+    let upload = find_pending_upload(&state, &upload_id).await?;
+    discard_pending_upload(&state, &upload).await?;
+
+    Ok(Json(DeleteResponse {
+        success: true,
+        message: "Upload aborted".to_string(),
+    }))
+}
+
+fn claim_upload(state: &AppState, upload_id: &str) -> Result<UploadClaim, ApiError> {
+    state.claim_upload(upload_id).ok_or_else(|| {
+        ApiError::Conflict(format!(
+            "Upload {} is already being written by another request",
+            upload_id
+        ))
+    })
+}
+
+async fn find_pending_upload(state: &AppState, upload_id: &str) -> Result<PendingUpload, ApiError> {
+    state
+        .database
+        .get_pending_upload(upload_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound("Upload not found".to_string()))
+}
+
+/// Only the final part may be shorter than `MIN_PART_SIZE`, so one left behind by a
+/// failed completion has to be sent again before more parts can follow it. Returns
+/// the number of the first part dropped.
+fn drop_short_part(upload: &mut PendingUpload) -> Option<u32> {
+    let full_parts = upload
+        .parts
+        .iter()
+        .take_while(|part| part.size as u64 == MIN_PART_SIZE)
+        .count();
+    let first_dropped = upload.parts.get(full_parts)?.part_number;
+    upload.parts.truncate(full_parts);
+    Some(first_dropped)
+}
+
+fn uploaded_bytes(upload: &PendingUpload) -> u64 {
+    upload.parts.iter().map(|part| part.size as u64).sum()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateUploadLinkRequest {
+    pub bucket: Option<String>,
+    pub endpoint: Option<String>,
+    pub prefix: String,
+    pub expires_in_seconds: i64,
+    pub max_uploads: Option<u32>,
+    pub max_file_size: Option<u64>,
+    pub allowed_content_types: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UploadLinkResponse {
+    pub id: String,
+    pub url: String,
+    pub bucket: Option<String>,
+    pub endpoint: Option<String>,
+    pub prefix: String,
+    pub expires_at: String,
+    pub max_uploads: Option<i64>,
+    pub uploads_served: i64,
+    pub max_file_size: Option<i64>,
+    pub allowed_content_types: Vec<String>,
+    pub created_at: String,
+    pub is_expired: bool,
+}
+
+impl UploadLinkResponse {
+    fn from_link(link: UploadLink, config: &AppConfig) -> Self {
+        Self {
+            url: format!("{}{}", config.upload_base_url(), link.id),
+            id: link.id,
+            bucket: link.bucket,
+            endpoint: link.endpoint,
+            prefix: link.prefix,
+            expires_at: link.expires_at.to_rfc3339(),
+            max_uploads: link.max_uploads,
+            uploads_served: link.uploads_served,
+            max_file_size: link.max_file_size,
+            allowed_content_types: link.allowed_content_types,
+            created_at: link.created_at.to_rfc3339(),
+            is_expired: link.is_expired,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListUploadLinksResponse {
+    pub links: Vec<UploadLinkResponse>,
+    pub total: usize,
+}
+
+/// What an anonymous holder may upload through a link
+#[derive(Debug, Serialize)]
+pub struct UploadLinkInfo {
+    pub expires_at: String,
+    pub remaining_uploads: Option<i64>,
+    pub max_file_size: Option<i64>,
+    pub allowed_content_types: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct ListObjectsQuery {
-    pub bucket: String,
-    pub prefix: Option<String>,
-    #[serde(rename = "continuation-token")]
-    pub continuation_token: Option<String>,
+pub struct StartUploadRequest {
+    pub filename: String,
+    pub size: u64,
+    pub content_type: Option<String>,
+    /// Defaults to multipart for files over 100 MiB
+    pub multipart: Option<bool>,
 }
 
-async fn list_objects(
+#[derive(Debug, Serialize)]
+pub struct PresignedPart {
+    pub part_number: u32,
+    #[serde(flatten)]
+    pub request: PresignedRequest,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum StartUploadResponse {
+    Put {
+        object_key: String,
+        request: PresignedRequest,
+        complete_url: String,
+    },
+    Multipart {
+        object_key: String,
+        upload_id: String,
+        part_size: u64,
+        parts: Vec<PresignedPart>,
+        complete_url: String,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompleteUploadRequest {
+    pub object_key: String,
+    /// Only for multipart uploads
+    pub upload_id: Option<String>,
+    #[serde(default)]
+    pub parts: Vec<CompletedPart>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompleteUploadResponse {
+    pub object_key: String,
+    pub size: u64,
+    pub etag: Option<String>,
+}
+
+const MIB: u64 = 1024 * 1024;
+// Larger files default to multipart; a single PUT is capped by OSS at 5 GiB
+const MULTIPART_THRESHOLD: u64 = 100 * MIB;
+const MAX_PUT_SIZE: u64 = 5 * 1024 * MIB;
+const MIN_PART_SIZE: u64 = 8 * MIB;
+const MAX_PARTS: u64 = 10_000;
+// Stale upload-link sessions are looked for hourly, and kept for a while after their
+// URLs expire so a part already in flight can still finish
+const UPLOAD_SESSION_SWEEP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+const UPLOAD_SESSION_GRACE_MINUTES: i64 = 60;
+
+async fn create_upload_link(
     _user: AuthUser,
     State(state): State<AppState>,
-    Query(query): Query<ListObjectsQuery>,
-) -> Result<Json<crate::oss_client::ListObjectsResponse>, ApiError> {
-    if query.bucket.is_empty() {
-        return Err(ApiError::BadRequest("Bucket name is required".to_string()));
+    Json(payload): Json<CreateUploadLinkRequest>,
+) -> Result<Json<UploadLinkResponse>, ApiError> {
+    let prefix = payload.prefix.trim().trim_matches('/').to_string();
+    if prefix.is_empty()
+        || prefix
+            .split('/')
+            .any(|segment| segment.is_empty() || segment == "..")
+    {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid prefix: {}",
+            payload.prefix
+        )));
+    }
+    if payload.bucket.is_none() && state.config.aliyun_default_bucket.is_none() {
+        return Err(ApiError::BadRequest(
+            SigningError::MissingBucket.to_string(),
+        ));
+    }
+    if payload.max_file_size == Some(0) {
+        return Err(ApiError::BadRequest(
+            "max_file_size must be greater than zero".to_string(),
+        ));
     }
 
-    let client = OssClient::new(state.config.as_ref())
-        .map_err(|e| ApiError::Internal(format!("Failed to create OSS client: {}", e)))?;
+    let allowed_content_types = payload
+        .allowed_content_types
+        .unwrap_or_default()
+        .iter()
+        .map(|value| value.trim().to_ascii_lowercase())
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<_>>();
+    if let Some(invalid) = allowed_content_types
+        .iter()
+        .find(|value| !value.contains('/'))
+    {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid content type: {}",
+            invalid
+        )));
+    }
 
-    let response = client
-        .list_objects(
-            &query.bucket,
-            query.prefix.as_deref(),
-            query.continuation_token.as_deref(),
+    let expires_in = if payload.expires_in_seconds > 0 {
+        payload.expires_in_seconds
+    } else {
+        state.config.default_expiry_secs
+    };
+
+    let id = generate_upload_link_id(&state).await?;
+    state
+        .database
+        .create_upload_link(NewUploadLink {
+            id: id.clone(),
+            bucket: payload.bucket,
+            endpoint: payload.endpoint,
+            prefix,
+            expires_at: Utc::now() + Duration::seconds(expires_in),
+            max_uploads: payload.max_uploads,
+            max_file_size: payload.max_file_size,
+            allowed_content_types,
+        })
+        .await
+        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?;
+
+    let link = find_upload_link(&state, &id).await?;
+    Ok(Json(UploadLinkResponse::from_link(link, &state.config)))
+}
+
+async fn list_upload_links(
+    _user: AuthUser,
+    Query(params): Query<ListLinksQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ListUploadLinksResponse>, ApiError> {
+    let links: Vec<UploadLinkResponse> = state
+        .database
+        .list_upload_links(params.limit, params.offset)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?
+        .into_iter()
+        .map(|link| UploadLinkResponse::from_link(link, &state.config))
+        .collect();
+
+    Ok(Json(ListUploadLinksResponse {
+        total: links.len(),
+        links,
+    }))
+}
+
+async fn delete_upload_link(
+    _user: AuthUser,
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<DeleteResponse>, ApiError> {
+    let deleted = state
+        .database
+        .delete_upload_link(&id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?;
+
+    Ok(Json(DeleteResponse {
+        success: deleted,
+        message: if deleted {
+            "Upload link deleted successfully".to_string()
+        } else {
+            "Upload link not found".to_string()
+        },
+    }))
+}
+
+async fn upload_link_info(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<UploadLinkInfo>, ApiError> {
+    let link = find_upload_link(&state, &id).await?;
+    if link.is_expired {
+        return Err(upload_link_gone());
+    }
+
+    Ok(Json(UploadLinkInfo {
+        expires_at: link.expires_at.to_rfc3339(),
+        remaining_uploads: link
+            .max_uploads
+            .map(|max| (max - link.uploads_served).max(0)),
+        max_file_size: link.max_file_size,
+        allowed_content_types: link.allowed_content_types,
+    }))
+}
+
+/// Hand out presigned URLs for one file; this is what counts against `max_uploads`
+async fn start_upload(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    Json(payload): Json<StartUploadRequest>,
+) -> Result<Json<StartUploadResponse>, ApiError> {
+    let link = find_upload_link(&state, &id).await?;
+    if link.is_expired {
+        return Err(upload_link_gone());
+    }
+
+    let filename = sanitize_upload_filename(&payload.filename)?;
+    let content_type = payload
+        .content_type
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .unwrap_or("application/octet-stream")
+        .to_ascii_lowercase();
+    if !content_type_allowed(&content_type, &link.allowed_content_types) {
+        return Err(ApiError::BadRequest(format!(
+            "Content type {} is not allowed",
+            content_type
+        )));
+    }
+    if let Some(max) = link.max_file_size
+        && payload.size > max as u64
+    {
+        return Err(ApiError::BadRequest(format!(
+            "File is larger than the {} byte limit",
+            max
+        )));
+    }
+
+    let (bucket, endpoint) = upload_destination(&state.config, &link)?;
+    let object_key = format!("{}/{}", link.prefix, filename);
+
+    // Uploads never overwrite; refuse before a slot is used up
+    let existing = head_object(&state, &bucket, &object_key, link.endpoint.as_deref())
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to check object: {}", e)))?;
+    if existing.is_some() {
+        return Err(ApiError::Conflict(format!(
+            "A file named '{}' has already been uploaded",
+            filename
+        )));
+    }
+
+    let consumed = state
+        .database
+        .consume_upload(&link.id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?;
+    if !consumed {
+        return Err(upload_link_gone());
+    }
+
+    // Starting again replaces an abandoned attempt at the same file
+    if let Some(previous) = state
+        .database
+        .get_upload_session(&link.id, &object_key)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?
+    {
+        discard_upload_session(&state, &previous).await?;
+    }
+
+    // V4 URLs are valid for at most seven days, whatever the link allows
+    let urls_expire_at = link
+        .expires_at
+        .min(Utc::now() + Duration::seconds(V4_MAX_EXPIRES_SECS));
+    let mut session = UploadSession {
+        link_id: link.id.clone(),
+        object_key: object_key.clone(),
+        upload_id: None,
+        bucket: bucket.clone(),
+        endpoint: link.endpoint.clone(),
+        expires_at: urls_expire_at,
+    };
+
+    let target = UploadTarget {
+        bucket: &bucket,
+        endpoint: &endpoint,
+        object_key: &object_key,
+        expires_at: urls_expire_at,
+    };
+    let complete_url = format!("{}{}/complete", state.config.upload_base_url(), link.id);
+
+    let multipart = payload
+        .multipart
+        .unwrap_or(payload.size > MULTIPART_THRESHOLD)
+        || payload.size > MAX_PUT_SIZE;
+    if !multipart {
+        let request = build_presigned_put(&state.config, &target, &content_type, payload.size)
+            .map_err(ApiError::Signing)?;
+        record_upload_session(&state, &session).await?;
+        return Ok(Json(StartUploadResponse::Put {
+            object_key,
+            request,
+            complete_url,
+        }));
+    }
+
+    // Every part URL is signed for its exact length, so the parts add up to the
+    // declared size, which was checked against the link's limit above
+    let part_size = MIN_PART_SIZE.max(payload.size.div_ceil(MAX_PARTS));
+    let part_lengths = part_lengths(payload.size, part_size);
+    if part_size > MAX_PUT_SIZE
+        || part_lengths.len() as u64 > MAX_PARTS
+        || part_lengths.iter().sum::<u64>() != payload.size
+    {
+        return Err(ApiError::BadRequest(format!(
+            "Cannot split {} bytes into at most {} parts",
+            payload.size, MAX_PARTS
+        )));
+    }
+
+    let client = oss_client(&state)?;
+    let upload_id = client
+        .initiate_multipart_upload(
+            &ObjectLocation {
+                bucket: &bucket,
+                object_key: &object_key,
+                endpoint_override: link.endpoint.as_deref(),
+            },
+            &content_type,
+            true,
         )
         .await
-        .map_err(|e| ApiError::Internal(format!("Failed to list objects: {}", e)))?;
+        .map_err(|e| ApiError::Internal(format!("Failed to start multipart upload: {}", e)))?;
 
-    Ok(Json(response))
+    session.upload_id = Some(upload_id.clone());
+    if let Err(err) = record_upload_session(&state, &session).await {
+        let _ = discard_multipart_upload(&state, &session, &upload_id).await;
+        return Err(err);
+    }
+
+    let parts = (1..)
+        .zip(part_lengths)
+        .map(|(part_number, length)| {
+            build_presigned_part(&state.config, &target, &upload_id, part_number, length).map(
+                |request| PresignedPart {
+                    part_number,
+                    request,
+                },
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ApiError::Signing)?;
+
+    Ok(Json(StartUploadResponse::Multipart {
+        object_key,
+        upload_id,
+        part_size,
+        parts,
+        complete_url,
+    }))
+}
+
+/// Finish an upload started with `start_upload` and verify the stored object against
+/// the link's limits
+async fn complete_upload(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    Json(payload): Json<CompleteUploadRequest>,
+) -> Result<Json<CompleteUploadResponse>, ApiError> {
+    let link = find_upload_link(&state, &id).await?;
+
+    // Only keys this link handed out, with the upload ID issued for them
+    let session = state
+        .database
+        .get_upload_session(&link.id, &payload.object_key)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?
+        .ok_or_else(|| {
+            ApiError::BadRequest("No upload of this object was started with this link".to_string())
+        })?;
+    if payload.upload_id != session.upload_id {
+        return Err(ApiError::BadRequest(
+            "Upload ID does not match the upload started for this object".to_string(),
+        ));
+    }
+
+    // The upload slot was taken by start_upload, so a used-up link may still finish
+    // the uploads it started, but not after it expires
+    if link.expires_at < Utc::now() {
+        discard_upload_session(&state, &session).await?;
+        return Err(upload_link_gone());
+    }
+
+    let location = ObjectLocation {
+        bucket: &session.bucket,
+        object_key: &session.object_key,
+        endpoint_override: session.endpoint.as_deref(),
+    };
+    let client = oss_client(&state)?;
+
+    if let Some(upload_id) = &session.upload_id {
+        if payload.parts.is_empty() {
+            return Err(ApiError::BadRequest("No parts to complete".to_string()));
+        }
+        let mut parts = payload.parts.clone();
+        parts.sort_by_key(|part| part.part_number);
+        client
+            .complete_multipart_upload(&location, upload_id, &parts, true)
+            .await
+            .map_err(|e| ApiError::Internal(format!("Failed to complete upload: {}", e)))?;
+    }
+
+    let metadata = head_object(
+        &state,
+        &session.bucket,
+        &session.object_key,
+        session.endpoint.as_deref(),
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("Failed to check object: {}", e)))?
+    .ok_or_else(|| ApiError::NotFound(format!("'{}' has not been uploaded", session.object_key)))?;
+
+    state
+        .database
+        .delete_upload_session(&session.link_id, &session.object_key)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?;
+
+    // Upload URLs are signed for the declared size; this catches anything else
+    if let Some(max) = link.max_file_size
+        && metadata.size > max as u64
+    {
+        client
+            .delete_object(
+                &session.bucket,
+                &session.object_key,
+                session.endpoint.as_deref(),
+            )
+            .await
+            .map_err(|e| ApiError::Internal(format!("Failed to remove oversized upload: {}", e)))?;
+        return Err(ApiError::BadRequest(format!(
+            "File is larger than the {} byte limit and has been removed",
+            max
+        )));
+    }
+
+    Ok(Json(CompleteUploadResponse {
+        object_key: session.object_key,
+        size: metadata.size,
+        etag: metadata.etag,
+    }))
+}
+
+async fn record_upload_session(state: &AppState, session: &UploadSession) -> Result<(), ApiError> {
+    state
+        .database
+        .create_upload_session(session)
+        .await
+        .map_err(|e| match e.downcast_ref::<sqlx::Error>() {
+            Some(sqlx::Error::Database(db)) if db.is_unique_violation() => ApiError::Conflict(
+                format!("'{}' is already being uploaded", session.object_key),
+            ),
+            _ => ApiError::Internal(format!("Database error: {}", e)),
+        })
+}
+
+/// Abort the session's multipart upload, if any, and forget the session
+async fn discard_upload_session(state: &AppState, session: &UploadSession) -> Result<(), ApiError> {
+    if let Some(upload_id) = &session.upload_id {
+        discard_multipart_upload(state, session, upload_id).await?;
+    }
+    state
+        .database
+        .delete_upload_session(&session.link_id, &session.object_key)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?;
+    Ok(())
+}
+
+async fn discard_multipart_upload(
+    state: &AppState,
+    session: &UploadSession,
+    upload_id: &str,
+) -> Result<(), ApiError> {
+    oss_client(state)?
+        .abort_multipart_upload(
+            &ObjectLocation {
+                bucket: &session.bucket,
+                object_key: &session.object_key,
+                endpoint_override: session.endpoint.as_deref(),
+            },
+            upload_id,
+        )
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to abort upload: {}", e)))
+}
+
+/// Abort upload-link uploads whose URLs have stopped working, and `POST /objects/upload`
+/// uploads left unfinished past `PENDING_UPLOAD_EXPIRY_HOURS`, so their parts do not
+/// stay billed in OSS. Runs for the life of the process.
+pub async fn sweep_upload_sessions(state: AppState) {
+    let mut interval = tokio::time::interval(UPLOAD_SESSION_SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let before = Utc::now() - Duration::minutes(UPLOAD_SESSION_GRACE_MINUTES);
+        match state.database.list_stale_upload_sessions(before).await {
+            Ok(sessions) => {
+                for session in sessions {
+                    if let Err(err) = discard_upload_session(&state, &session).await {
+                        eprintln!(
+                            "Failed to discard stale upload of {}: {:?}",
+                            session.object_key, err
+                        );
+                    }
+                }
+            }
+            Err(err) => eprintln!("Failed to list stale uploads: {}", err),
+        }
+
+        let before = Utc::now() - Duration::hours(state.config.pending_upload_expiry_hours);
+        match state.database.list_stale_pending_uploads(before).await {
+            Ok(uploads) => {
+                for upload in uploads {
+                    // Still being written to; its expiry is checked again next round
+                    let Some(_claim) = state.claim_upload(&upload.upload_id) else {
+                        continue;
+                    };
+                    if let Err(err) = discard_pending_upload(&state, &upload).await {
+                        eprintln!(
+                            "Failed to discard unfinished upload of {}: {:?}",
+                            upload.object_key, err
+                        );
+                    }
+                }
+            }
+            Err(err) => eprintln!("Failed to list unfinished uploads: {}", err),
+        }
+    }
+}
+
+/// Abort the multipart upload in OSS and forget it; the caller holds its claim
+async fn discard_pending_upload(state: &AppState, upload: &PendingUpload) -> Result<(), ApiError> {
+    oss_client(state)?
+        .abort_multipart_upload(
+            &ObjectLocation {
+                bucket: &upload.bucket,
+                object_key: &upload.object_key,
+                endpoint_override: upload.endpoint.as_deref(),
+            },
+            &upload.upload_id,
+        )
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to abort upload: {}", e)))?;
+    state
+        .database
+        .delete_pending_upload(&upload.upload_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?;
+    Ok(())
+}
+
+// Full parts of `part_size` and a shorter last one; a single empty part for an empty file
+fn part_lengths(size: u64, part_size: u64) -> Vec<u64> {
+    let full_parts = size / part_size;
+    let mut lengths = vec![part_size; full_parts as usize];
+    if !size.is_multiple_of(part_size) || lengths.is_empty() {
+        lengths.push(size % part_size);
+    }
+    lengths
+}
+
+async fn find_download_link(state: &AppState, id_or_slug: &str) -> Result<DownloadLink, ApiError> {
+    state
+        .database
+        .get_download_link(id_or_slug)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound("Link not found".to_string()))
+}
+
+async fn find_upload_link(state: &AppState, id: &str) -> Result<UploadLink, ApiError> {
+    state
+        .database
+        .get_upload_link(id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound("Upload link not found".to_string()))
+}
+
+fn upload_link_gone() -> ApiError {
+    ApiError::Gone("Upload link has expired or has been used up".to_string())
+}
+
+fn upload_destination(config: &AppConfig, link: &UploadLink) -> Result<(String, String), ApiError> {
+    let bucket = link
+        .bucket
+        .clone()
+        .or_else(|| config.aliyun_default_bucket.clone())
+        .ok_or(ApiError::Signing(SigningError::MissingBucket))?;
+    let endpoint = link
+        .endpoint
+        .clone()
+        .or_else(|| config.aliyun_default_endpoint.clone())
+        .ok_or(ApiError::Signing(SigningError::MissingEndpoint))?;
+    Ok((bucket, endpoint))
+}
+
+// Keep only the final path component so uploads cannot escape the link's prefix
+fn sanitize_upload_filename(filename: &str) -> Result<String, ApiError> {
+    let name = filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim();
+    if name.is_empty()
+        || name == "."
+        || name == ".."
+        || name.len() > 255
+        || name.chars().any(char::is_control)
+    {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid filename: {}",
+            filename
+        )));
+    }
+    Ok(name.to_string())
+}
+
+// Patterns are exact types or `type/*`; an empty list allows everything
+fn content_type_allowed(content_type: &str, allowed: &[String]) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    allowed.is_empty()
+        || allowed
+            .iter()
+            .any(|pattern| match pattern.strip_suffix("/*") {
+                Some(top_level) => essence
+                    .split_once('/')
+                    .is_some_and(|(kind, _)| kind == top_level),
+                None => pattern == essence,
+            })
+}
+
+async fn generate_upload_link_id(state: &AppState) -> Result<String, ApiError> {
+    let format = state.config.link_id_format;
+    for _ in 0..LINK_ID_ATTEMPTS {
+        let id = format.generate();
+        let taken = state
+            .database
+            .get_upload_link(&id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("Database error: {}", e)))?
+            .is_some();
+        if !taken {
+            return Ok(id);
+        }
+    }
+
+    Err(ApiError::Internal(
+        "Could not allocate a unique link ID".to_string(),
+    ))
 }
 
 #[derive(Debug)]
 pub enum ApiError {
     BadRequest(String),
+    NotFound(String),
+    Conflict(String),
+    Gone(String),
     Internal(String),
-    #[allow(dead_code)]
     Signing(SigningError),
     #[allow(dead_code)]
     Unauthorized,
@@ -476,6 +3319,9 @@ impl IntoResponse for ApiError {
     fn into_response(self) -> axum::response::Response {
         let (status, message) = match self {
             ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            ApiError::Conflict(msg) => (StatusCode::CONFLICT, msg),
+            ApiError::Gone(msg) => (StatusCode::GONE, msg),
             ApiError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
             ApiError::Signing(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
             ApiError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized".to_string()),
@@ -490,3 +3336,101 @@ impl IntoResponse for ApiError {
         (status, Json(ErrorResponse { message })).into_response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tickets_with(
+        link_id: &str,
+        downloads_served: u32,
+    ) -> Arc<RwLock<HashMap<String, DownloadTicket>>> {
+        let ticket = DownloadTicket {
+            id: link_id.to_string(),
+            bucket_override: None,
+            object_key: "report.pdf".to_string(),
+            expires_at: Utc::now() + Duration::hours(1),
+            max_downloads: Some(3),
+            downloads_served,
+            created_at: Utc::now(),
+            download_filename: None,
+            disposition: None,
+            recipient: Some("bob@example.com".to_string()),
+            watermark: Some(WatermarkMode::Visible),
+            endpoint_override: None,
+            allowed_cidrs: Vec::new(),
+            bind_client_ip: false,
+            use_intranet: None,
+            process: None,
+            response_content_type: None,
+            response_cache_control: None,
+            response_content_language: None,
+            landing_page: false,
+            restore_ready_at: None,
+        };
+        Arc::new(RwLock::new(HashMap::from([(link_id.to_string(), ticket)])))
+    }
+
+    async fn served(tickets: &RwLock<HashMap<String, DownloadTicket>>, link_id: &str) -> u32 {
+        tickets.read().await[link_id].downloads_served
+    }
+
+    /// Stands in for the watermarking step of `serve_watermarked`
+    async fn stream_watermarked(
+        mut reservation: DownloadReservation,
+        fail: bool,
+    ) -> Result<(), DownloadError> {
+        if fail {
+            return Err(DownloadError::Rejected(
+                StatusCode::BAD_GATEWAY,
+                "Failed to fetch the file from storage".to_string(),
+            ));
+        }
+        reservation.delivered = true;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn gives_the_download_back_when_streaming_fails() {
+        let tickets = tickets_with("link-1", 1);
+        let reservation = DownloadReservation {
+            tickets: tickets.clone(),
+            link_id: "link-1".to_string(),
+            delivered: false,
+        };
+
+        assert!(stream_watermarked(reservation, true).await.is_err());
+        assert_eq!(served(&tickets, "link-1").await, 0);
+    }
+
+    #[tokio::test]
+    async fn keeps_the_download_once_delivered() {
+        let tickets = tickets_with("link-1", 1);
+        let reservation = DownloadReservation {
+            tickets: tickets.clone(),
+            link_id: "link-1".to_string(),
+            delivered: false,
+        };
+
+        assert!(stream_watermarked(reservation, false).await.is_ok());
+        assert_eq!(served(&tickets, "link-1").await, 1);
+    }
+
+    #[tokio::test]
+    async fn refunds_later_when_the_tickets_are_locked() {
+        let tickets = tickets_with("link-1", 2);
+        let guard = tickets.read().await;
+        drop(DownloadReservation {
+            tickets: tickets.clone(),
+            link_id: "link-1".to_string(),
+            delivered: false,
+        });
+        assert_eq!(guard["link-1"].downloads_served, 2);
+        drop(guard);
+
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(served(&tickets, "link-1").await, 1);
+    }
+}
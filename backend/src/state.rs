@@ -1,33 +1,95 @@
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 
 use chrono::{DateTime, Utc};
-use tokio::sync::RwLock;
-use uuid::Uuid;
+use ipnet::IpNet;
+use tokio::sync::{RwLock, Semaphore};
 
 use crate::config::AppConfig;
+use crate::content_disposition::DispositionType;
 use crate::database::Database;
+use crate::jwt_keys::JwtKeys;
+use crate::metrics::Metrics;
+use crate::oauth::OAuthSession;
+use crate::oidc::OidcProvider;
+use crate::oss_client::OssClient;
+use crate::rate_limit::RateLimiter;
+use crate::watermark::WatermarkMode;
 
 #[derive(Clone)]
 pub struct AppState {
     pub config: Arc<AppConfig>,
-    pub tickets: Arc<RwLock<HashMap<Uuid, DownloadTicket>>>,
+    pub tickets: Arc<RwLock<HashMap<String, DownloadTicket>>>,
+    pub slugs: Arc<RwLock<HashMap<String, String>>>,
     pub database: Database,
+    pub jwt_keys: Arc<JwtKeys>,
+    pub oidc: Option<Arc<OidcProvider>>,
+    pub oauth_sessions: Arc<RwLock<HashMap<String, OAuthSession>>>,
+    pub rate_limiter: Arc<RateLimiter>,
+    /// `None` when no default endpoint is configured
+    pub oss: Option<Arc<OssClient>>,
+    pub metrics: Arc<Metrics>,
+    /// Bounds the watermarked downloads held in memory at once
+    pub watermark_jobs: Arc<Semaphore>,
+    /// Multipart uploads a request is writing to right now
+    pub active_uploads: Arc<Mutex<HashSet<String>>>,
 }
 
 impl AppState {
-    pub fn new(config: AppConfig, database: Database) -> Self {
+    pub fn new(
+        config: AppConfig,
+        database: Database,
+        jwt_keys: JwtKeys,
+        oidc: Option<OidcProvider>,
+        rate_limiter: RateLimiter,
+        oss: Option<OssClient>,
+    ) -> Self {
         Self {
+            watermark_jobs: Arc::new(Semaphore::new(config.watermark_max_concurrent)),
             config: Arc::new(config),
             tickets: Arc::new(RwLock::new(HashMap::new())),
+            slugs: Arc::new(RwLock::new(HashMap::new())),
             database,
+            jwt_keys: Arc::new(jwt_keys),
+            oidc: oidc.map(Arc::new),
+            oauth_sessions: Arc::new(RwLock::new(HashMap::new())),
+            rate_limiter: Arc::new(rate_limiter),
+            oss: oss.map(Arc::new),
+            metrics: Arc::new(Metrics::default()),
+            active_uploads: Arc::new(Mutex::new(HashSet::new())),
         }
     }
+
+    /// Reserve a multipart upload for one request so concurrent resumes cannot hand out
+    /// the same part numbers; `None` while another request holds it
+    pub fn claim_upload(&self, upload_id: &str) -> Option<UploadClaim> {
+        let mut active = self.active_uploads.lock().unwrap();
+        if !active.insert(upload_id.to_string()) {
+            return None;
+        }
+        Some(UploadClaim {
+            active_uploads: self.active_uploads.clone(),
+            upload_id: upload_id.to_string(),
+        })
+    }
+}
+
+/// Releases the upload when dropped
+pub struct UploadClaim {
+    active_uploads: Arc<Mutex<HashSet<String>>>,
+    upload_id: String,
+}
+
+impl Drop for UploadClaim {
+    fn drop(&mut self) {
+        self.active_uploads.lock().unwrap().remove(&self.upload_id);
+    }
 }
 
+#[derive(Clone)]
 pub struct DownloadTicket {
     #[allow(dead_code)]
-    pub id: Uuid,
+    pub id: String,
     pub bucket_override: Option<String>,
     pub object_key: String,
     pub expires_at: DateTime<Utc>,
@@ -36,5 +98,32 @@ pub struct DownloadTicket {
     #[allow(dead_code)]
     pub created_at: DateTime<Utc>,
     pub download_filename: Option<String>,
+    pub disposition: Option<DispositionType>,
+    pub recipient: Option<String>,
+    /// Set for links that are served through the backend with the recipient marked in
+    pub watermark: Option<WatermarkMode>,
     pub endpoint_override: Option<String>,
+    pub allowed_cidrs: Vec<IpNet>,
+    pub bind_client_ip: bool,
+    /// `None` picks the intranet host only for downloaders in the internal networks
+    pub use_intranet: Option<bool>,
+    pub process: Option<String>,
+    pub response_content_type: Option<String>,
+    pub response_cache_control: Option<String>,
+    pub response_content_language: Option<String>,
+    pub landing_page: bool,
+    /// Set while the archived object is being restored; estimated completion
+    pub restore_ready_at: Option<DateTime<Utc>>,
+}
+
+impl DownloadTicket {
+    /// Whether the download URL must ask OSS to transform or relabel the object
+    pub fn overrides_response(&self) -> bool {
+        self.download_filename.is_some()
+            || self.disposition.is_some()
+            || self.process.is_some()
+            || self.response_content_type.is_some()
+            || self.response_cache_control.is_some()
+            || self.response_content_language.is_some()
+    }
 }
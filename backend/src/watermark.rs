@@ -0,0 +1,538 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream, text_string};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+// Resource name of the font the visible mark is drawn with
+const FONT_NAME: &[u8] = b"DlWatermarkFont";
+const FONT_SIZE: f32 = 8.0;
+// Letter size, for pages whose MediaBox cannot be read
+const DEFAULT_MEDIA_BOX: [f32; 4] = [0.0, 0.0, 612.0, 792.0];
+// Ancestors walked to find an inherited page attribute, guarding against cycles
+const MAX_PAGE_TREE_DEPTH: usize = 64;
+
+/// How a recipient-bound link marks the file it serves
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatermarkMode {
+    /// A footer on every page, plus the document metadata
+    Visible,
+    /// Document metadata only; the pages are untouched
+    Metadata,
+}
+
+impl WatermarkMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WatermarkMode::Visible => "visible",
+            WatermarkMode::Metadata => "metadata",
+        }
+    }
+}
+
+impl FromStr for WatermarkMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "visible" => Ok(WatermarkMode::Visible),
+            "metadata" => Ok(WatermarkMode::Metadata),
+            other => Err(format!("expected visible or metadata, got {:?}", other)),
+        }
+    }
+}
+
+/// Who a copy was issued to, and when
+pub struct Watermark<'a> {
+    pub recipient: &'a str,
+    pub link_id: &'a str,
+    pub issued_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Error)]
+pub enum WatermarkError {
+    #[error("Encrypted PDFs cannot be watermarked")]
+    Encrypted,
+    #[error("Invalid PDF: {0}")]
+    InvalidPdf(#[from] lopdf::Error),
+    #[error("Failed to write PDF: {0}")]
+    Write(#[from] std::io::Error),
+}
+
+/// Only PDFs can be watermarked; judged by Content-Type, else by extension
+pub fn is_supported(object_key: &str, content_type: Option<&str>) -> bool {
+    match content_type.map(|value| value.trim().to_ascii_lowercase()) {
+        Some(content_type) if content_type != "application/octet-stream" => {
+            content_type.starts_with("application/pdf")
+        }
+        _ => object_key.to_ascii_lowercase().ends_with(".pdf"),
+    }
+}
+
+/// Returns a copy of `pdf` carrying the recipient in its Info dictionary and, for
+/// `Visible`, in a footer on every page
+pub fn watermark_pdf(
+    pdf: &[u8],
+    mode: WatermarkMode,
+    watermark: &Watermark<'_>,
+) -> Result<Vec<u8>, WatermarkError> {
+    let mut document = Document::load_mem(pdf)?;
+    // lopdf opens files with an empty user password; saving would drop the encryption
+    if document.was_encrypted() {
+        return Err(WatermarkError::Encrypted);
+    }
+
+    let issued_at = watermark.issued_at.format("%Y-%m-%d %H:%M UTC").to_string();
+    set_info(&mut document, watermark, &issued_at)?;
+
+    if mode == WatermarkMode::Visible {
+        let font_id = document.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type1".to_vec())),
+            ("BaseFont", Object::Name(b"Helvetica".to_vec())),
+            ("Encoding", Object::Name(b"WinAnsiEncoding".to_vec())),
+        ]));
+        let text = format!(
+            "Issued to {} on {} (link {})",
+            watermark.recipient, issued_at, watermark.link_id
+        );
+        let pages: Vec<ObjectId> = document.get_pages().into_values().collect();
+        for page_id in pages {
+            stamp_page(&mut document, page_id, font_id, &text)?;
+        }
+    }
+
+    let mut output = Vec::with_capacity(pdf.len() + 4096);
+    document.save_to(&mut output)?;
+    Ok(output)
+}
+
+fn set_info(
+    document: &mut Document,
+    watermark: &Watermark<'_>,
+    issued_at: &str,
+) -> Result<(), WatermarkError> {
+    let info_id = match document.trailer.get(b"Info").and_then(Object::as_reference) {
+        Ok(id) if document.get_dictionary(id).is_ok() => id,
+        _ => {
+            let id = document.add_object(Dictionary::new());
+            document.trailer.set("Info", Object::Reference(id));
+            id
+        }
+    };
+
+    let info = document.get_dictionary_mut(info_id)?;
+    info.set("Recipient", text_string(watermark.recipient));
+    info.set("IssuedAt", text_string(issued_at));
+    info.set("DownloadLink", text_string(watermark.link_id));
+    Ok(())
+}
+
+/// Draw `text` along the bottom of the page. The page's own content is wrapped in
+/// q/Q so whatever graphics state it leaves behind cannot move or hide the mark.
+fn stamp_page(
+    document: &mut Document,
+    page_id: ObjectId,
+    font_id: ObjectId,
+    text: &str,
+) -> Result<(), WatermarkError> {
+    let media_box = inherited_attribute(document, page_id, b"MediaBox")
+        .and_then(|value| {
+            let values = value.as_array().ok()?;
+            let values: Vec<f32> = values.iter().filter_map(|v| v.as_float().ok()).collect();
+            <[f32; 4]>::try_from(values).ok()
+        })
+        .unwrap_or(DEFAULT_MEDIA_BOX);
+
+    add_font_resource(document, page_id, font_id)?;
+
+    let existing = match document.get_dictionary(page_id)?.get(b"Contents") {
+        Ok(Object::Reference(id)) => match document.get_object(*id) {
+            // Contents may point at an array of streams rather than a stream
+            Ok(Object::Array(streams)) => streams.clone(),
+            _ => vec![Object::Reference(*id)],
+        },
+        Ok(Object::Array(streams)) => streams.clone(),
+        _ => Vec::new(),
+    };
+
+    let content = format!(
+        "Q\nq\nBT\n/{} {} Tf\n0.45 g\n1 0 0 1 {} {} Tm\n({}) Tj\nET\nQ\n",
+        String::from_utf8_lossy(FONT_NAME),
+        FONT_SIZE,
+        media_box[0] + 18.0,
+        media_box[1] + 12.0,
+        escape_pdf_text(text)
+    );
+    let open_id = document.add_object(Stream::new(Dictionary::new(), b"q\n".to_vec()));
+    let stamp_id = document.add_object(Stream::new(Dictionary::new(), content.into_bytes()));
+
+    let mut contents = Vec::with_capacity(existing.len() + 2);
+    contents.push(Object::Reference(open_id));
+    contents.extend(existing);
+    contents.push(Object::Reference(stamp_id));
+    document
+        .get_dictionary_mut(page_id)?
+        .set("Contents", Object::Array(contents));
+    Ok(())
+}
+
+/// Register the watermark font in the page's resources. Inherited resources are
+/// copied onto the page first, since a page's own dictionary replaces its parent's.
+fn add_font_resource(
+    document: &mut Document,
+    page_id: ObjectId,
+    font_id: ObjectId,
+) -> Result<(), WatermarkError> {
+    let own_resources = document
+        .get_dictionary(page_id)?
+        .get(b"Resources")
+        .ok()
+        .cloned();
+    let resources_id = match own_resources {
+        Some(Object::Reference(id)) if document.get_dictionary(id).is_ok() => Some(id),
+        Some(Object::Dictionary(_)) => None,
+        _ => {
+            let inherited = inherited_attribute(document, page_id, b"Resources")
+                .and_then(|value| value.as_dict().ok().cloned())
+                .unwrap_or_default();
+            document
+                .get_dictionary_mut(page_id)?
+                .set("Resources", Object::Dictionary(inherited));
+            None
+        }
+    };
+
+    let fonts = match resources_id {
+        Some(id) => document.get_dictionary(id)?.get(b"Font").ok().cloned(),
+        None => document
+            .get_dictionary(page_id)?
+            .get(b"Resources")
+            .and_then(Object::as_dict)?
+            .get(b"Font")
+            .ok()
+            .cloned(),
+    };
+
+    // A shared font dictionary gains the entry for every page that uses it, which is harmless
+    if let Some(Object::Reference(fonts_id)) = fonts
+        && let Ok(fonts) = document.get_dictionary_mut(fonts_id)
+    {
+        fonts.set(FONT_NAME, Object::Reference(font_id));
+        return Ok(());
+    }
+
+    let mut fonts = match fonts {
+        Some(Object::Dictionary(fonts)) => fonts,
+        _ => Dictionary::new(),
+    };
+    fonts.set(FONT_NAME, Object::Reference(font_id));
+    let resources = match resources_id {
+        Some(id) => document.get_dictionary_mut(id)?,
+        None => document
+            .get_dictionary_mut(page_id)?
+            .get_mut(b"Resources")
+            .and_then(Object::as_dict_mut)?,
+    };
+    resources.set("Font", Object::Dictionary(fonts));
+    Ok(())
+}
+
+/// The page's own value for `key`, or the nearest ancestor's, with references resolved
+fn inherited_attribute(document: &Document, page_id: ObjectId, key: &[u8]) -> Option<Object> {
+    let mut node_id = page_id;
+    for _ in 0..MAX_PAGE_TREE_DEPTH {
+        let node = document.get_dictionary(node_id).ok()?;
+        if let Ok(value) = node.get(key) {
+            return match value {
+                Object::Reference(id) => document.get_object(*id).ok().cloned(),
+                value => Some(value.clone()),
+            };
+        }
+        node_id = node.get(b"Parent").and_then(Object::as_reference).ok()?;
+    }
+    None
+}
+
+// Only ASCII is drawn with the standard Helvetica font; the metadata keeps the full name
+fn escape_pdf_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::encryption::{EncryptionState, EncryptionVersion, Permissions};
+    use lopdf::{StringFormat, decode_text_string};
+
+    use super::*;
+
+    const PAGE_OPS: &str = "BT /F1 12 Tf 72 720 Td (Hello) Tj ET\n";
+
+    fn issued(recipient: &str) -> Watermark<'_> {
+        Watermark {
+            recipient,
+            link_id: "link-1",
+            issued_at: DateTime::parse_from_rfc3339("2024-05-01T08:30:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+        }
+    }
+
+    fn stream(document: &mut Document, content: &str) -> Object {
+        Object::Reference(
+            document.add_object(Stream::new(Dictionary::new(), content.as_bytes().to_vec())),
+        )
+    }
+
+    fn font(document: &mut Document) -> Object {
+        Object::Reference(document.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type1".to_vec())),
+            ("BaseFont", Object::Name(b"Times-Roman".to_vec())),
+        ])))
+    }
+
+    /// A one-level page tree: `tree` is merged into the Pages node, each of `pages`
+    /// becomes a Page under it
+    fn build_pdf(mut document: Document, tree: Dictionary, pages: Vec<Dictionary>) -> Vec<u8> {
+        let pages_id = document.new_object_id();
+        let kids: Vec<Object> = pages
+            .into_iter()
+            .map(|mut page| {
+                page.set("Type", Object::Name(b"Page".to_vec()));
+                page.set("Parent", Object::Reference(pages_id));
+                Object::Reference(document.add_object(page))
+            })
+            .collect();
+
+        let mut node = Dictionary::from_iter([
+            ("Type", Object::Name(b"Pages".to_vec())),
+            ("Count", Object::Integer(kids.len() as i64)),
+            (
+                "MediaBox",
+                Object::Array(vec![0.into(), 0.into(), 595.into(), 842.into()]),
+            ),
+        ]);
+        node.set("Kids", Object::Array(kids));
+        node.extend(&tree);
+        document.objects.insert(pages_id, Object::Dictionary(node));
+
+        let catalog_id = document.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", Object::Reference(pages_id)),
+        ]));
+        document.trailer.set("Root", Object::Reference(catalog_id));
+
+        let mut output = Vec::new();
+        document.save_to(&mut output).unwrap();
+        output
+    }
+
+    fn single_page_pdf() -> Vec<u8> {
+        let mut document = Document::with_version("1.5");
+        let contents = stream(&mut document, PAGE_OPS);
+        let resources = Dictionary::from_iter([(
+            "Font",
+            Object::Dictionary(Dictionary::from_iter([("F1", font(&mut document))])),
+        )]);
+        let page = Dictionary::from_iter([
+            ("Contents", contents),
+            ("Resources", Object::Dictionary(resources)),
+        ]);
+        build_pdf(document, Dictionary::new(), vec![page])
+    }
+
+    fn watermark(pdf: &[u8], mode: WatermarkMode, recipient: &str) -> Document {
+        let marked = watermark_pdf(pdf, mode, &issued(recipient)).unwrap();
+        Document::load_mem(&marked).unwrap()
+    }
+
+    fn info(document: &Document, key: &[u8]) -> String {
+        let info_id = document
+            .trailer
+            .get(b"Info")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        decode_text_string(document.get_dictionary(info_id).unwrap().get(key).unwrap()).unwrap()
+    }
+
+    fn page_ids(document: &Document) -> Vec<ObjectId> {
+        document.get_pages().into_values().collect()
+    }
+
+    fn content(document: &Document, page_id: ObjectId) -> String {
+        String::from_utf8(document.get_page_content(page_id).unwrap()).unwrap()
+    }
+
+    fn font_names(document: &Document, page_id: ObjectId) -> Vec<Vec<u8>> {
+        document
+            .get_page_fonts(page_id)
+            .unwrap()
+            .into_keys()
+            .collect()
+    }
+
+    #[test]
+    fn records_the_recipient_in_the_info_dictionary() {
+        let document = watermark(&single_page_pdf(), WatermarkMode::Metadata, "Zoë 张伟");
+        assert_eq!(info(&document, b"Recipient"), "Zoë 张伟");
+        assert_eq!(info(&document, b"IssuedAt"), "2024-05-01 08:30 UTC");
+        assert_eq!(info(&document, b"DownloadLink"), "link-1");
+
+        // Metadata mode leaves the pages alone
+        let page_id = page_ids(&document)[0];
+        assert_eq!(content(&document, page_id), PAGE_OPS);
+        assert_eq!(font_names(&document, page_id), vec![b"F1".to_vec()]);
+    }
+
+    #[test]
+    fn wraps_a_single_content_stream_and_appends_the_mark() {
+        let document = watermark(
+            &single_page_pdf(),
+            WatermarkMode::Visible,
+            "bob@example.com",
+        );
+        let page_id = page_ids(&document)[0];
+
+        let content = content(&document, page_id);
+        let mark = "(Issued to bob@example.com on 2024-05-01 08:30 UTC (link link-1)) Tj";
+        let mark = mark.replace("(link link-1)", "\\(link link-1\\)");
+        assert!(content.starts_with(&format!("q\n{}Q\nq\nBT\n/DlWatermarkFont", PAGE_OPS)));
+        assert!(content.contains(&mark), "{}", content);
+        assert!(content.ends_with("ET\nQ\n"));
+        assert_eq!(
+            font_names(&document, page_id),
+            vec![b"DlWatermarkFont".to_vec(), b"F1".to_vec()]
+        );
+    }
+
+    #[test]
+    fn keeps_every_stream_of_a_contents_array_in_order() {
+        let mut document = Document::with_version("1.5");
+        let first = stream(&mut document, "0 0 m\n");
+        let second = stream(&mut document, "10 10 l S\n");
+        let page = Dictionary::from_iter([("Contents", Object::Array(vec![first, second]))]);
+        let pdf = build_pdf(document, Dictionary::new(), vec![page]);
+
+        let document = watermark(&pdf, WatermarkMode::Visible, "bob");
+        let content = content(&document, page_ids(&document)[0]);
+        assert!(content.starts_with("q\n0 0 m\n10 10 l S\nQ\nq\nBT\n"));
+        assert!(content.contains("(Issued to bob on "));
+    }
+
+    #[test]
+    fn copies_inherited_resources_onto_the_page() {
+        let mut document = Document::with_version("1.5");
+        let contents = stream(&mut document, PAGE_OPS);
+        let inherited = Dictionary::from_iter([(
+            "Font",
+            Object::Dictionary(Dictionary::from_iter([("F1", font(&mut document))])),
+        )]);
+        let tree = Dictionary::from_iter([("Resources", Object::Dictionary(inherited))]);
+        let pdf = build_pdf(
+            document,
+            tree,
+            vec![Dictionary::from_iter([("Contents", contents)])],
+        );
+
+        let document = watermark(&pdf, WatermarkMode::Visible, "bob");
+        let page_id = page_ids(&document)[0];
+        let page = document.get_dictionary(page_id).unwrap();
+        let fonts = page
+            .get(b"Resources")
+            .and_then(Object::as_dict)
+            .and_then(|resources| resources.get(b"Font"))
+            .and_then(Object::as_dict)
+            .unwrap();
+        // The page keeps the parent's font next to ours
+        assert!(fonts.has(b"F1"));
+        assert!(fonts.has(FONT_NAME));
+
+        // The Pages node itself is untouched
+        let parent_id = page.get(b"Parent").unwrap().as_reference().unwrap();
+        let parent_fonts = document
+            .get_dictionary(parent_id)
+            .and_then(|parent| parent.get(b"Resources"))
+            .and_then(Object::as_dict)
+            .and_then(|resources| resources.get(b"Font"))
+            .and_then(Object::as_dict)
+            .unwrap();
+        assert!(!parent_fonts.has(FONT_NAME));
+    }
+
+    #[test]
+    fn adds_the_font_once_to_a_shared_font_dictionary() {
+        let mut document = Document::with_version("1.5");
+        let f1 = font(&mut document);
+        let fonts_id = document.add_object(Dictionary::from_iter([("F1", f1)]));
+        let pages = (0..2)
+            .map(|_| {
+                let contents = stream(&mut document, PAGE_OPS);
+                let resources = Dictionary::from_iter([("Font", Object::Reference(fonts_id))]);
+                Dictionary::from_iter([
+                    ("Contents", contents),
+                    ("Resources", Object::Dictionary(resources)),
+                ])
+            })
+            .collect();
+        let pdf = build_pdf(document, Dictionary::new(), pages);
+
+        let document = watermark(&pdf, WatermarkMode::Visible, "bob");
+        for page_id in page_ids(&document) {
+            assert_eq!(
+                font_names(&document, page_id),
+                vec![b"DlWatermarkFont".to_vec(), b"F1".to_vec()]
+            );
+            assert!(content(&document, page_id).contains("(Issued to bob on "));
+        }
+    }
+
+    #[test]
+    fn escapes_the_drawn_text() {
+        assert_eq!(escape_pdf_text(r"a(b)c\d"), r"a\(b\)c\\d");
+        assert_eq!(escape_pdf_text("Zoë 张"), "Zo? ?");
+
+        let document = watermark(&single_page_pdf(), WatermarkMode::Visible, r"(x)\ 张");
+        let content = content(&document, page_ids(&document)[0]);
+        assert!(content.contains(r"(Issued to \(x\)\\ ? on "), "{}", content);
+        // The metadata keeps the name as given
+        assert_eq!(info(&document, b"Recipient"), r"(x)\ 张");
+    }
+
+    #[test]
+    fn refuses_encrypted_pdfs() {
+        let mut document = Document::load_mem(&single_page_pdf()).unwrap();
+        let id = Object::String(b"0123456789abcdef".to_vec(), StringFormat::Hexadecimal);
+        document
+            .trailer
+            .set("ID", Object::Array(vec![id.clone(), id]));
+        let state = EncryptionState::try_from(EncryptionVersion::V2 {
+            document: &document,
+            owner_password: "owner",
+            user_password: "",
+            key_length: 128,
+            permissions: Permissions::default(),
+        })
+        .unwrap();
+        document.encrypt(&state).unwrap();
+        let mut pdf = Vec::new();
+        document.save_to(&mut pdf).unwrap();
+
+        let result = watermark_pdf(&pdf, WatermarkMode::Metadata, &issued("bob"));
+        assert!(matches!(result, Err(WatermarkError::Encrypted)));
+    }
+}
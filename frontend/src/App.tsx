@@ -41,18 +41,12 @@ import type {
   ListLinksResponse,
   ListObjectsResponse,
   LoginResponse,
+  OAuthLoginResponse,
   ObjectInfo,
   UserInfoResponse,
 } from "./types";
-import { API_CONFIG, OAUTH_CONFIG } from "./config";
-import {
-  buildAuthorizeUrl,
-  generateCodeChallenge,
-  generateCodeVerifier,
-  generateState,
-  getAndClearOAuthSession,
-  storeOAuthSession,
-} from "./oauth";
+import { API_CONFIG } from "./config";
+import { getAndClearOAuthSession, storeOAuthSession } from "./oauth";
 
 const TOKEN_STORAGE_KEY = "signed-download-token";
 const TOKEN_EXPIRY_STORAGE_KEY = "signed-download-token-exp";
@@ -145,8 +139,9 @@ const App = () => {
               params: {
                 code,
                 state,
-                code_verifier: session.codeVerifier,
               },
+              // Sends the state cookie set by /api/oauth/login
+              withCredentials: true,
             }
           );
 
@@ -306,26 +301,29 @@ const App = () => {
   }, [linkForm.bucket, fetchObjects]);
 
   const handleLogin = useCallback(async () => {
-    // Generate PKCE parameters
-    const codeVerifier = generateCodeVerifier();
-    const codeChallenge = await generateCodeChallenge(codeVerifier);
-    const state = generateState();
-
-    // Store to sessionStorage
-    storeOAuthSession(state, codeVerifier);
-
-    // Build authorization URL and redirect
-    const authorizeUrl = buildAuthorizeUrl(
-      OAUTH_CONFIG.AUTHORIZE_URL,
-      OAUTH_CONFIG.CLIENT_ID,
-      OAUTH_CONFIG.REDIRECT_URI,
-      state,
-      codeChallenge,
-      OAUTH_CONFIG.SCOPE
-    );
-
-    window.location.href = authorizeUrl;
-  }, []);
+    try {
+      // Backend generates state, nonce and PKCE parameters
+      const response = await axios.get<OAuthLoginResponse>(
+        `${API_CONFIG.BASE_URL}/api/oauth/login`,
+        { withCredentials: true }
+      );
+      const { authorize_url, state } = response.data;
+
+      // Store to sessionStorage
+      storeOAuthSession(state);
+
+      window.location.href = authorize_url;
+    } catch (error) {
+      console.error("OAuth login error:", error);
+      toast({
+        title: "登录失败",
+        description: "无法发起 OAuth2 登录，请稍后重试。",
+        status: "error",
+        duration: 3000,
+        isClosable: true,
+      });
+    }
+  }, [toast]);
 
   const handleLogout = useCallback(() => {
     setAuthToken(null);
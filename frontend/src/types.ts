@@ -4,6 +4,12 @@ export interface LoginResponse {
   username?: string;
 }
 
+export interface OAuthLoginResponse {
+  authorize_url: string;
+  state: string;
+  expires_in: number;
+}
+
 export interface UserInfoResponse {
   username: string;
 }
@@ -14,14 +20,36 @@ export interface CreateLinkRequest {
   expires_in_seconds?: number;
   max_downloads?: number;
   download_filename?: string;
+  disposition?: 'inline' | 'attachment';
+  recipient?: string;
+  watermark?: 'visible' | 'metadata';
   endpoint?: string;
+  allowed_cidrs?: string[];
+  bind_client_ip?: boolean;
+  use_intranet?: boolean;
+  process?: string;
+  response_content_type?: string;
+  response_cache_control?: string;
+  response_content_language?: string;
+  slug?: string;
+  landing_page?: boolean;
+  restore?: boolean;
+  qr?: QrOptions;
+}
+
+export interface QrOptions {
+  format?: 'svg' | 'png';
+  size?: number;
+  error_correction?: 'L' | 'M' | 'Q' | 'H';
 }
 
 export interface CreateLinkResponse {
   id: string;
+  slug?: string;
   url: string;
   expires_at: string;
   max_downloads?: number;
+  qr_code?: string;
 }
 
 export interface DownloadLinkResponse {
@@ -33,7 +61,23 @@ export interface DownloadLinkResponse {
   downloads_served: number;
   created_at: string;
   download_filename?: string;
+  disposition?: 'inline' | 'attachment';
+  recipient?: string;
+  watermark?: 'visible' | 'metadata';
   endpoint?: string;
+  allowed_cidrs: string[];
+  bind_client_ip?: boolean;
+  use_intranet?: boolean;
+  process?: string;
+  response_content_type?: string;
+  response_cache_control?: string;
+  response_content_language?: string;
+  slug?: string;
+  landing_page: boolean;
+  preview_hits: number;
+  object_size?: number;
+  object_etag?: string;
+  restore_ready_at?: string;
   is_expired: boolean;
   download_url: string;
 }
@@ -43,6 +87,36 @@ export interface ListLinksResponse {
   total: number;
 }
 
+export interface CreateUploadLinkRequest {
+  bucket?: string;
+  endpoint?: string;
+  prefix: string;
+  expires_in_seconds?: number;
+  max_uploads?: number;
+  max_file_size?: number;
+  allowed_content_types?: string[];
+}
+
+export interface UploadLinkResponse {
+  id: string;
+  url: string;
+  bucket?: string;
+  endpoint?: string;
+  prefix: string;
+  expires_at: string;
+  max_uploads?: number;
+  uploads_served: number;
+  max_file_size?: number;
+  allowed_content_types: string[];
+  created_at: string;
+  is_expired: boolean;
+}
+
+export interface ListUploadLinksResponse {
+  links: UploadLinkResponse[];
+  total: number;
+}
+
 export interface Bucket {
   name: string;
   location: string;
@@ -63,8 +137,134 @@ export interface ObjectInfo {
   storage_class: string;
 }
 
+export interface ObjectMetadata {
+  size: number;
+  last_modified?: string;
+  etag?: string;
+  content_type?: string;
+  storage_class?: string;
+  restore?: string;
+  user_metadata: Record<string, string>;
+}
+
+export interface ListObjectsParams {
+  bucket: string;
+  prefix?: string;
+  delimiter?: string;
+  'continuation-token'?: string;
+  'start-after'?: string;
+  'max-keys'?: number;
+  sort?: 'key' | 'last_modified' | 'size';
+  order?: 'asc' | 'desc';
+}
+
 export interface ListObjectsResponse {
   objects: ObjectInfo[];
+  common_prefixes: string[];
   is_truncated: boolean;
   next_continuation_token?: string;
 }
+
+export interface UploadObjectParams {
+  bucket?: string;
+  key?: string;
+  prefix?: string;
+  endpoint?: string;
+  content_type?: string;
+  overwrite?: boolean;
+  upload_id?: string;
+  create_link?: boolean;
+  link_expires_in_seconds?: number;
+  link_max_downloads?: number;
+}
+
+export interface UploadObjectResponse {
+  bucket: string;
+  object_key: string;
+  size: number;
+  etag?: string;
+  link?: CreateLinkResponse;
+}
+
+export interface UploadedPart {
+  part_number: number;
+  etag: string;
+  size: number;
+}
+
+export interface PendingUploadResponse {
+  upload_id: string;
+  bucket: string;
+  endpoint?: string;
+  object_key: string;
+  content_type: string;
+  created_at: string;
+  part_size: number;
+  bytes_uploaded: number;
+  parts: UploadedPart[];
+}
+
+export interface DeleteObjectResponse {
+  bucket: string;
+  object_key: string;
+  revoked_links: string[];
+}
+
+export interface CopyObjectRequest {
+  source_bucket?: string;
+  source_key: string;
+  destination_bucket?: string;
+  destination_key: string;
+  endpoint?: string;
+  overwrite?: boolean;
+}
+
+export interface RenameObjectRequest extends CopyObjectRequest {
+  revoke_links?: boolean;
+}
+
+export interface CopyObjectResponse {
+  bucket: string;
+  object_key: string;
+  size: number;
+  etag?: string;
+  revoked_links: string[];
+}
+
+export interface CacheStats {
+  hits: number;
+  misses: number;
+  hit_rate: number;
+  entries: number;
+}
+
+export interface OssStatsResponse {
+  bucket_location_cache: CacheStats;
+}
+
+export interface SearchObjectsParams {
+  bucket: string;
+  prefix?: string;
+  glob?: string;
+  regex?: string;
+  min_size?: number;
+  max_size?: number;
+  modified_after?: string;
+  modified_before?: string;
+  storage_class?: string;
+  'start-after'?: string;
+  max_scan?: number;
+  limit?: number;
+}
+
+// One line of the NDJSON stream returned by GET /objects/search
+export type SearchEvent =
+  | ({ type: 'object' } & ObjectInfo)
+  | {
+      type: 'done';
+      scanned: number;
+      matched: number;
+      truncated: boolean;
+      resume_after?: string;
+    }
+  | { type: 'error'; message: string };